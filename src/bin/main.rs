@@ -1,25 +1,40 @@
 #[macro_use]
 extern crate serde_derive;
 
-use ansi_term::Colour::Yellow;
 use ansi_term::{ANSIString, ANSIStrings};
 
+use std::collections::HashMap;
 use std::default::Default;
+use std::env;
+use std::io;
 use std::io::Cursor;
+use std::io::Write;
 use std::path;
+use std::process;
 
 use docopt::Docopt;
 
 //use skim::{Skim, SkimOptionsBuilder};
 
-use rusty_x::{Snippet, show_multiple_results};
-use rusty_x::{edit_snippet, start_operation, Error, OpCode, Project, ProjectOperation};
+use rusty_x::{
+    copy_to_clipboard, read_body, read_code_blocks, read_content, ClipboardConfig, CodeBlock,
+};
+use rusty_x::{
+    delete_snippet, edit_snippet, expand_aliases, move_snippet, rename_snippet, start_operation,
+    write_snippets, ColorMode, Error, Format, OpCode, Project, ProjectOperation, SearchOptions,
+    Strictness, Theme,
+};
+use rusty_x::{fill_placeholders, find_placeholders, Placeholder, RecentValues};
+use rusty_x::{run_code, RunConfig};
+use rusty_x::{show_multiple_results, show_results_with_preview, Action, PickerOptions, Snippet};
 
 const USAGE: &'static str = "\
-Usage: x
+Usage: x [--color=<when>] [--first | --all | --list | --format=<format>]
        x [--add=<filename>] <keywords>...
        x --new
-       x [--edit] <keywords>...
+       x --aliases [--and] [--] <keywords>...
+       x [--edit | --copy | --code=<lang> | --run] [--block=<n>] [--set=<var>...] [--body] [--and] [--match=<mode>] [--height=<height>] [--color=<when>] [--first | --all | --list | --format=<format>] [--] <keywords>...
+       x (--rm | --mv=<name> | --move-to=<location>) [--first] [--] <keywords>...
        x --pull
        x --save
 
@@ -28,6 +43,24 @@ Options:
     --new                Add a new snippet without a given name and you need to fill in the keywords
     --add=<filename>     Add a new snippet with given filename and keywords
     -e, --edit           Edit a existing snippet
+    -c, --copy           Copy the first code block of the snippet to the clipboard
+    --code=<lang>        Only print the code blocks, of the language when given, e.g. --code=bash
+    -r, --run            Run the first code block of the snippet, after confirmation
+    --block=<n>          Copy, print or run the n-th code block instead, starting at 1
+    --set=<var>          Fill in a placeholder in the code, e.g. --set pod=web-1
+    -b, --body           Also search the keywords in the content of the snippets
+    -a, --and            Only find snippets that match all keywords
+    --match=<mode>       How keywords match words: exact, word, prefix or fuzzy
+    --height=<height>    Show the picker below the cursor with a height in rows or percent, e.g. 40%
+    --color=<when>       Use colors: auto, always or never
+    --first              Only show the best match, without the picker
+    --all                Show all matches, without the picker
+    --list               Print the paths, titles, descriptions and tags of the matches, without the picker
+    --format=<format>    Print the matches as json, ndjson or tsv
+    --aliases            Show which aliases are used for the keywords
+    --rm                 Delete a snippet, after confirmation
+    --mv=<name>          Rename a snippet in its folder
+    --move-to=<location>  Move a snippet to another snippet location
     --pull               Sync snippet repo (git pull)
     --save               Save snippet repo (git add, git commit, git push)
 \
@@ -39,27 +72,369 @@ struct Args {
     flag_add: String,
     flag_new: bool,
     flag_edit: bool,
+    flag_copy: bool,
+    flag_run: bool,
+    flag_block: String,
+    flag_code: Option<String>,
+    flag_set: Vec<String>,
+    flag_body: bool,
+    flag_and: bool,
+    flag_match: String,
+    flag_height: String,
+    flag_color: String,
+    flag_first: bool,
+    flag_all: bool,
+    flag_list: bool,
+    flag_format: String,
+    flag_aliases: bool,
+    flag_rm: bool,
+    flag_mv: String,
+    flag_move_to: String,
     flag_pull: bool,
     flag_save: bool,
 }
 
+/// How the found snippets are shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Output {
+    /// Choose in the picker when there are multiple snippets
+    Picker,
+    /// Only the best match
+    First,
+    All,
+    /// The paths and titles of the snippets
+    List,
+}
+
+/// Where `--mv` and `--move-to` move a snippet
+enum Destination {
+    /// A new name in the folder of the snippet
+    Name(String),
+    /// The path of a snippet location
+    Location(String),
+}
+
+/// What is done with the chosen snippets
+struct Actions<'a> {
+    /// Performed instead of viewing, e.g. `Edit` with `--edit`
+    default: Action,
+    theme: &'a Theme,
+    project: &'a Project,
+    destination: Option<Destination>,
+    clipboard: ClipboardConfig,
+    run: RunConfig,
+    /// Only show the code, of the language when not empty
+    code: Option<String>,
+    /// The code block to copy or show, starting at 1
+    block: Option<usize>,
+    /// Values for the placeholders in the code
+    values: HashMap<String, String>,
+}
+
+impl<'a> Actions<'a> {
+    /// The language of the code blocks to use, any when not set
+    fn language(&self) -> Option<&str> {
+        self.code.as_deref().filter(|l| !l.is_empty())
+    }
+}
+
 /// Display the snippet on the command line
-fn display_snippet(full_path: &path::Path) {
-    let printer = prettyprint::PrettyPrinter::default()
+fn display_snippet(snippet: &Snippet, theme: &Theme) -> Result<(), Error> {
+    // Only the section for a snippet in a split file
+    let content = read_content(&snippet.name, anchor(snippet))?;
+
+    // Print the snippet as is when the output is not a terminal, e.g. in a script, unless colors
+    // are asked for with --color=always
+    let terminal = atty::is(atty::Stream::Stdout);
+    if !terminal && !theme.colored {
+        print!("{}", content);
+        return Ok(());
+    }
+
+    let mut builder = prettyprint::PrettyPrinter::default();
+    builder
         .language("markdown")
         .header(false)
         .line_numbers(false)
-        .build()
-        .unwrap();
+        .colored_output(theme.colored);
+    // Write to the pipe instead of starting a pager
+    if !terminal {
+        builder.paging_mode(prettyprint::PagingMode::Never);
+    }
+    if let Some(ref syntax_theme) = theme.syntax_theme {
+        builder.theme(syntax_theme.as_str());
+    }
+    let printer = builder.build().unwrap();
+
+    printer.string(content).unwrap();
+    Ok(())
+}
+
+/// The anchor of the snippet when it is a section
+fn anchor(snippet: &Snippet) -> Option<&str> {
+    snippet.anchor.as_deref()
+}
+
+/// Align the cells in columns as wide as their widest cell, up to the maximum width of the
+/// column. Longer cells are cut off, empty columns are left out
+fn align_columns(rows: &[Vec<String>], max_widths: &[usize]) -> Vec<String> {
+    let widths: Vec<usize> = max_widths
+        .iter()
+        .enumerate()
+        .map(|(i, max)| {
+            let widest = rows.iter().map(|r| r[i].chars().count()).max().unwrap_or(0);
+            widest.min(*max)
+        })
+        .collect();
+
+    rows.iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .filter(|(_, width)| **width > 0)
+                .map(|(cell, &width)| {
+                    let length = cell.chars().count();
+                    if length > width {
+                        let mut cut: String = cell.chars().take(width - 1).collect();
+                        cut.push('…');
+                        cut
+                    } else {
+                        format!("{}{}", cell, " ".repeat(width - length))
+                    }
+                })
+                .collect();
+            cells.join("  ").trim_end().to_string()
+        })
+        .collect()
+}
+
+/// Print a line with the path, title, description and tags of every snippet. The columns are
+/// aligned in a terminal and separated by tabs otherwise
+fn list_snippets(snippets: &[Snippet]) {
+    let terminal = atty::is(atty::Stream::Stdout);
+    let rows: Vec<Vec<String>> = snippets
+        .iter()
+        .map(|s| {
+            vec![
+                s.full_name(),
+                s.display_title(),
+                s.description.clone().unwrap_or_default(),
+                s.tags.join(", "),
+            ]
+        })
+        .collect();
+
+    if terminal {
+        for line in align_columns(&rows, &[60, 30, 50, 30]) {
+            println!("{}", line);
+        }
+    } else {
+        for row in rows {
+            println!("{}", row.join("\t"));
+        }
+    }
+}
+
+/// Ask for the value of the placeholder on stderr, so the output can still be captured. The
+/// default, or else the most recent value, is used when nothing is entered
+fn ask_value(placeholder: &Placeholder, recent: &[String]) -> Result<String, Error> {
+    let name = &placeholder.name;
+    if !atty::is(atty::Stream::Stdin) {
+        return placeholder.default.clone().ok_or_else(|| {
+            Error::InternalError(format!(
+                "No value for `{}`, set it with --set {}=<value>",
+                name, name
+            ))
+        });
+    }
+
+    let suggestion = placeholder.default.as_ref().or_else(|| recent.first());
+    let mut prompt = name.to_string();
+    if let Some(suggestion) = suggestion {
+        prompt.push_str(&format!(" [{}]", suggestion));
+    }
+    let others: Vec<&str> = recent
+        .iter()
+        .filter(|v| Some(*v) != suggestion)
+        .take(3)
+        .map(|v| v.as_str())
+        .collect();
+    if !others.is_empty() {
+        prompt.push_str(&format!(" (recent: {})", others.join(", ")));
+    }
+
+    loop {
+        eprint!("{}: ", prompt);
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer)? == 0 {
+            return Err(Error::InternalError(format!(
+                "No value entered for `{}`",
+                name
+            )));
+        }
+        let answer = answer.trim_end_matches(['\n', '\r']);
+        if !answer.is_empty() {
+            return Ok(answer.to_string());
+        }
+        if let Some(suggestion) = suggestion {
+            return Ok(suggestion.to_string());
+        }
+    }
+}
+
+/// Fill in the placeholders in the code, with the values given with `--set` or else asked for.
+/// The values are remembered to suggest them the next time
+fn fill_in(code: &str, actions: &Actions) -> Result<String, Error> {
+    let placeholders = find_placeholders(code);
+    if placeholders.is_empty() {
+        return Ok(code.to_string());
+    }
+
+    let mut recent = RecentValues::load();
+    let mut values = HashMap::new();
+    for placeholder in placeholders {
+        let value = match actions.values.get(&placeholder.name) {
+            Some(value) => value.to_string(),
+            None => ask_value(&placeholder, recent.get(&placeholder.name))?,
+        };
+        recent.add(&placeholder.name, &value);
+        values.insert(placeholder.name, value);
+    }
+    // Remembering the values is only a convenience, the code can be used without it
+    recent.save().ok();
+    Ok(fill_placeholders(code, &values))
+}
+
+/// The code blocks of the snippet in the language, or all of them when not given
+fn code_blocks(snippet: &Snippet, language: Option<&str>) -> Result<Vec<CodeBlock>, Error> {
+    let mut blocks = read_code_blocks(&snippet.name, anchor(snippet))?;
+    if let Some(language) = language {
+        blocks.retain(|b| {
+            b.language
+                .as_ref()
+                .is_some_and(|l| l.eq_ignore_ascii_case(language))
+        });
+    }
+    Ok(blocks)
+}
+
+/// The n-th code block of the snippet, starting at 1
+fn nth_code_block(name: &str, blocks: &[CodeBlock], n: usize) -> Result<String, Error> {
+    blocks
+        .get(n - 1)
+        .map(|b| b.code.clone())
+        .ok_or_else(|| Error::InternalError(format!("{} has no code block {}", name, n)))
+}
+
+/// Copy the code of the snippets to the clipboard, the chosen code block or the first one. The
+/// whole body is copied when a snippet has no code blocks
+fn copy_snippets(snippets: &[&Snippet], actions: &Actions) -> Result<(), Error> {
+    let mut text = String::new();
+    for snippet in snippets {
+        let blocks = code_blocks(snippet, actions.language())?;
+        match (actions.block, blocks.first()) {
+            (Some(n), _) => text.push_str(&nth_code_block(&snippet.full_name(), &blocks, n)?),
+            (None, Some(first)) => text.push_str(&first.code),
+            (None, None) if actions.language().is_none() => {
+                for (_, line) in read_body(&snippet.name, anchor(snippet))? {
+                    text.push_str(&line);
+                    text.push('\n');
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    if text.is_empty() {
+        eprintln!("Nothing to copy");
+        return Ok(());
+    }
+    let text = fill_in(&text, actions)?;
+    copy_to_clipboard(&text, &actions.clipboard)?;
+    let count = text.lines().count();
+    eprintln!(
+        "Copied {} {} to the clipboard",
+        count,
+        if count == 1 { "line" } else { "lines" }
+    );
+    Ok(())
+}
+
+/// Run the chosen code block of the snippet, or the first one, with the interpreter for its
+/// language after confirmation. Returns the exit code of the code, 0 when it is not run
+fn run_snippet(snippet: &Snippet, actions: &Actions) -> Result<i32, Error> {
+    let name = snippet.full_name();
+    let blocks = code_blocks(snippet, actions.language())?;
+    let block = match actions.block {
+        Some(n) => blocks
+            .get(n - 1)
+            .ok_or_else(|| Error::InternalError(format!("{} has no code block {}", name, n)))?,
+        None => blocks
+            .first()
+            .ok_or_else(|| Error::InternalError(format!("{} has no code blocks", name)))?,
+    };
+    let language = block.language.as_deref();
+    let interpreter = actions.run.interpreter(language).ok_or_else(|| {
+        Error::InternalError(format!(
+            "No interpreter for `{}`, add one to the [run.interpreters] config",
+            language.unwrap_or("")
+        ))
+    })?;
+    if !atty::is(atty::Stream::Stdin) {
+        return Err(Error::InternalError(
+            "Running code needs a terminal to confirm it".to_string(),
+        ));
+    }
+    let code = fill_in(&block.code, actions)?;
+
+    // Show what will be run, on stderr like the questions for the placeholders
+    eprint!("{}", code);
+    eprint!("Run with `{}`? [y/N] ", interpreter);
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if !answer.trim().eq_ignore_ascii_case("y") {
+        return Ok(0);
+    }
+
+    run_code(&interpreter, &code, &RunConfig::extension(language))
+}
 
-    printer
-        .file(full_path.as_os_str().to_str().unwrap())
-        .unwrap();
+/// Print the code blocks of the snippet as is, separated by an empty line, or only the chosen
+/// one
+fn print_code(snippet: &Snippet, actions: &Actions) -> Result<(), Error> {
+    let name = snippet.full_name();
+    let blocks = code_blocks(snippet, actions.language())?;
+    let code = match actions.block {
+        Some(n) => nth_code_block(&name, &blocks, n)?,
+        None if blocks.is_empty() => {
+            return Err(Error::InternalError(match actions.language() {
+                Some(language) => format!("{} has no {} code blocks", name, language),
+                None => format!("{} has no code blocks", name),
+            }))
+        }
+        None => {
+            let code: Vec<&str> = blocks.iter().map(|b| b.code.as_str()).collect();
+            code.join("\n")
+        }
+    };
+    print!("{}", fill_in(&code, actions)?);
+    Ok(())
 }
 
 fn main() -> Result<(), Error> {
+    // `--code` can be used without a language, but docopt needs a value for it
+    let mut keywords_only = false;
+    let argv = env::args().map(|arg| {
+        keywords_only = keywords_only || arg == "--";
+        if !keywords_only && arg == "--code" {
+            "--code=".to_string()
+        } else {
+            arg
+        }
+    });
     let args: Args = Docopt::new(USAGE)
-        .and_then(|d| d.deserialize())
+        .and_then(|d| d.argv(argv).deserialize())
         .unwrap_or_else(|e| e.exit());
 
     // Try to get the project file
@@ -86,12 +461,33 @@ fn main() -> Result<(), Error> {
         location.create_if_not_exists()?;
     }
 
+    // Keys and settings for the picker
+    let mut picker_config = project.picker_config();
+    if !args.flag_height.is_empty() {
+        picker_config.height = Some(args.flag_height.clone());
+    }
+    // Colors, the flag overrides the config
+    let theme_config = project.theme_config();
+    let color = if args.flag_color.is_empty() {
+        theme_config.color
+    } else {
+        args.flag_color
+            .parse::<ColorMode>()
+            .map_err(Error::InternalError)?
+    };
+    let theme = Theme::new(&theme_config, color)?;
+    let picker_options = PickerOptions::new(&project.key_config(), &picker_config, &theme)?;
+
     // Get mode of operation
     let op_code = if !args.flag_add.is_empty() || args.flag_new {
         // Convert to strings
         let results = project.locations.iter().map(|l| l.local.clone()).collect();
         // Only use the fist choice
-        let choice = show_multiple_results(&results);
+        let choice = match show_multiple_results(&results, &picker_options) {
+            Some(selection) => selection.indices,
+            // Cancelled
+            None => return Ok(()),
+        };
         // Return if no choice has been made
         if choice.len() == 0 {
             return Ok(());
@@ -113,27 +509,133 @@ fn main() -> Result<(), Error> {
 
     // Pass keywords or options
     let keywords: Vec<String> = args.arg_keywords;
+    let search_config = project.search_config();
+    let strictness = if args.flag_match.is_empty() {
+        search_config.strictness
+    } else {
+        args.flag_match
+            .parse::<Strictness>()
+            .map_err(Error::InternalError)?
+    };
+    let options = SearchOptions {
+        body: args.flag_body,
+        match_all: args.flag_and || search_config.match_all,
+        strictness,
+    };
+
+    let output = if args.flag_first {
+        Output::First
+    } else if args.flag_all {
+        Output::All
+    } else if args.flag_list {
+        Output::List
+    } else {
+        Output::Picker
+    };
+
+    // Only show the aliases used for the keywords
+    if args.flag_aliases {
+        let expanded = expand_aliases(&project, &keywords, &options)?;
+        if expanded.is_empty() {
+            println!("No aliases used for the keywords");
+        }
+        for (keyword, synonyms) in expanded {
+            println!("{} -> {}", keyword, synonyms.join(", "));
+        }
+        return Ok(());
+    }
+
+    // Print the matches in a machine readable format instead
+    if !args.flag_format.is_empty() {
+        let format = args
+            .flag_format
+            .parse::<Format>()
+            .map_err(Error::InternalError)?;
+        let snippets = start_operation(&op_code, &project, keywords, &options)?;
+        let stdout = io::stdout();
+        return write_snippets(&mut stdout.lock(), &snippets, &project, format);
+    }
+
+    let block = if args.flag_block.is_empty() {
+        None
+    } else {
+        match args.flag_block.parse::<usize>() {
+            Ok(n) if n > 0 => Some(n),
+            _ => {
+                return Err(Error::InternalError(format!(
+                    "Invalid code block `{}`, expected a number starting at 1",
+                    args.flag_block
+                )))
+            }
+        }
+    };
+    let mut values = HashMap::new();
+    for var in &args.flag_set {
+        match var.find('=') {
+            Some(equals) => values.insert(var[..equals].to_string(), var[equals + 1..].to_string()),
+            None => {
+                return Err(Error::InternalError(format!(
+                    "Invalid value `{}`, expected name=value",
+                    var
+                )))
+            }
+        };
+    }
+    let destination = if !args.flag_mv.is_empty() {
+        Some(Destination::Name(args.flag_mv.clone()))
+    } else if !args.flag_move_to.is_empty() {
+        Some(Destination::Location(args.flag_move_to.clone()))
+    } else {
+        None
+    };
+    let actions = Actions {
+        default: if args.flag_edit {
+            Action::Edit
+        } else if args.flag_copy {
+            Action::Copy
+        } else if args.flag_run {
+            Action::Run
+        } else if args.flag_rm {
+            Action::Delete
+        } else if destination.is_some() {
+            Action::Move
+        } else {
+            Action::View
+        },
+        theme: &theme,
+        project: &project,
+        destination,
+        clipboard: project.clipboard_config(),
+        run: project.run_config(),
+        code: args.flag_code,
+        block,
+        values,
+    };
 
     // Start processing with given arguments
-    start_operation(&op_code, &project, keywords)
-        .and_then(|snippets| process_snippets(op_code, &snippets))?;
+    let exit_code = start_operation(&op_code, &project, keywords, &options)
+        .and_then(|snippets| process_snippets(&snippets, output, &picker_options, &actions))?;
 
-    check_modified_files(&project)?;
+    check_modified_files(&project, &theme)?;
 
+    // Exit with the exit code of code that failed with --run
+    if exit_code != 0 {
+        process::exit(exit_code);
+    }
     Ok(())
 }
 
 /// Check if we have unsaved changes if so display
-fn check_modified_files(project: &Project) -> Result<(), Error> {
+fn check_modified_files(project: &Project, theme: &Theme) -> Result<(), Error> {
     for location in project.locations.iter().filter(|l| l.git == Some(true)) {
         // If this is a git location
         match rusty_x::determine_git_modified_status(location) {
             Ok(rusty_x::GitStatus::Modified) => {
                 let strings: &[ANSIString] = &[
-                    Yellow.bold().paint(&location.local),
-                    Yellow.paint(" has modified files"),
+                    theme.modified.bold().paint(&location.local),
+                    theme.modified.paint(" has modified files"),
                 ];
-                println!("{}", ANSIStrings(strings));
+                eprintln!("{}", ANSIStrings(strings));
                 Ok(())
             }
             // Don't need to show anything
@@ -146,51 +648,163 @@ fn check_modified_files(project: &Project) -> Result<(), Error> {
     Ok(())
 }
 
-fn process_snippets(op_code: OpCode, snippets: &Vec<Snippet>) -> Result<(), Error> {
-    let intermediate: Vec<String> = snippets
+/// Perform the action chosen in the picker on the snippets. Returns the exit code of the last
+/// code that failed when running snippets, or else 0
+fn perform_action(action: Action, snippets: &[&Snippet], actions: &Actions) -> Result<i32, Error> {
+    // Copy the snippets at once, so the clipboard has all of them
+    if action == Action::Copy {
+        return copy_snippets(snippets, actions).map(|_| 0);
+    }
+    if action == Action::Move {
+        return move_snippets(snippets, actions).map(|_| 0);
+    }
+
+    let mut exit_code = 0;
+    for snippet in snippets {
+        match action {
+            // TODO add x editor command
+            Action::Edit => edit_snippet("vim", path::Path::new(&snippet.name), anchor(snippet))?,
+            Action::View if actions.code.is_some() => print_code(snippet, actions)?,
+            Action::View => display_snippet(snippet, actions.theme)?,
+            Action::Copy | Action::Move => {}
+            Action::Run => match run_snippet(snippet, actions)? {
+                0 => {}
+                code => exit_code = code,
+            },
+            Action::Delete => {
+                print!("Delete {}? [y/N] ", snippet.full_name());
+                io::stdout().flush()?;
+                let mut answer = String::new();
+                io::stdin().read_line(&mut answer)?;
+                if answer.trim().eq_ignore_ascii_case("y") {
+                    delete_snippet(actions.project, snippet)?;
+                    println!("Deleted {}", snippet.name);
+                }
+            }
+        }
+    }
+    Ok(exit_code)
+}
+
+/// Rename the snippet or move the snippets to another location
+fn move_snippets(snippets: &[&Snippet], actions: &Actions) -> Result<(), Error> {
+    for snippet in snippets {
+        let moved = match actions.destination {
+            Some(Destination::Name(ref name)) => {
+                // All of them would get the same name
+                if snippets.len() > 1 {
+                    return Err(Error::InternalError(
+                        "Choose a single snippet to rename".to_string(),
+                    ));
+                }
+                rename_snippet(actions.project, snippet, name)?
+            }
+            Some(Destination::Location(ref location)) => {
+                move_snippet(actions.project, snippet, location)?
+            }
+            None => continue,
+        };
+        println!("Moved {} to {}", snippet.name, moved.display());
+    }
+    Ok(())
+}
+
+fn process_snippets(
+    snippets: &Vec<Snippet>,
+    output: Output,
+    picker_options: &PickerOptions,
+    actions: &Actions,
+) -> Result<i32, Error> {
+    match output {
+        Output::List => {
+            list_snippets(snippets);
+            return Ok(0);
+        }
+        Output::First | Output::All => {
+            let count = if output == Output::First {
+                1
+            } else {
+                snippets.len()
+            };
+            let chosen: Vec<&Snippet> = snippets.iter().take(count).collect();
+            return perform_action(actions.default, &chosen, actions);
+        }
+        Output::Picker => {}
+    }
+
+    // Show the title, description, tags and name in the location of each snippet
+    let rows: Vec<Vec<String>> = snippets
         .iter()
         .map(|s| {
-            s.tags
-                .join(", ")
-//                .iter()
-//                .fold(String::new(), |s, val| {
-//                    (s + ", "
-//                        + &format!("{}", val.trim())//ansi_term::Style::new().bold().paint(val.trim()))
-//                            .to_owned())
-//                })
-//                .replacen(",", "", 1)
+            vec![
+                s.display_title(),
+                s.description.clone().unwrap_or_default(),
+                s.tags.join(", "),
+                s.short_name(),
+            ]
+        })
+        .collect();
+    let intermediate: Vec<String> = align_columns(&rows, &[30, 30, 20, 30])
+        .into_iter()
+        .zip(snippets)
+        .map(|(row, s)| {
+            // Show the first line that matched in the body
+            match s.body_matches.first() {
+                Some(m) => format!("{}  [{}: {}]", row, m.line, m.text),
+                None => row,
+            }
         })
         .collect();
 
     // We have more than 1 result
     if intermediate.len() > 1 {
-        // Use library to do multiple selection for snippets
-        let to_show = show_multiple_results(&intermediate);
-
-        for i in to_show {
-            let snip = &snippets[i];
-            let full_path = path::Path::new(&snip.name);
-            // If we chose to edit the snippet use the edit command
-            // TODO add x editor command
-            if let OpCode::ListSnippets(true) = op_code {
-                edit_snippet("vim", full_path)?;
-            } else {
-                // Otherwise display
-                display_snippet(&full_path);
+        // The picker needs a terminal, list the snippets when used in a script
+        if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stdout) {
+            // Except for code, which is used like `$(x --code docker prune)`
+            if actions.code.is_some() {
+                return print_code(&snippets[0], actions).map(|_| 0);
             }
+            // Other actions would silently not be done
+            if actions.default != Action::View {
+                return Err(Error::InternalError(format!(
+                    "{} matches, use --first or narrow the query",
+                    snippets.len()
+                )));
+            }
+            list_snippets(snippets);
+            return Ok(0);
         }
+
+        // Use library to do multiple selection for snippets
+        let selection = match show_results_with_preview(&intermediate, picker_options, &|i| {
+            read_content(&snippets[i].name, anchor(&snippets[i])).unwrap_or_default()
+        }) {
+            Some(selection) => selection,
+            // Cancelled, so nothing to do
+            None => return Ok(0),
+        };
+
+        // If we chose to edit or copy the snippet do that instead of viewing
+        let action = match selection.action {
+            Action::View => actions.default,
+            action => action,
+        };
+
+        let chosen: Vec<&Snippet> = selection.indices.iter().map(|&i| &snippets[i]).collect();
+        perform_action(action, &chosen, actions)
     } else if intermediate.len() == 1 {
         // Display a single snippet
         let snip = &snippets[0];
-        let full_path = path::Path::new(&snip.name);
 
         // Same as above
-        if let OpCode::ListSnippets(true) = op_code {
-            edit_snippet("vim", full_path)?;
-        }
+        let exit_code = perform_action(actions.default, &[snip], actions)?;
 
-        // Display otherwise
-        display_snippet(&full_path);
+        // Display after editing
+        if actions.default == Action::Edit {
+            display_snippet(snip, actions.theme)?;
+        }
+        Ok(exit_code)
+    } else {
+        Ok(0)
     }
-    Ok(())
 }
@@ -0,0 +1,59 @@
+use crate::error::Error;
+use crate::project::ClipboardConfig;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Copy the text to the clipboard, with the configured command or else with an OSC 52 escape
+/// sequence
+pub fn copy_to_clipboard(text: &str, config: &ClipboardConfig) -> Result<(), Error> {
+    match config.command {
+        Some(ref command) => copy_with_command(text, command),
+        None => copy_with_osc52(text),
+    }
+}
+
+/// Pipe the text to the clipboard command, run by the shell so it can have arguments
+fn copy_with_command(text: &str, command: &str) -> Result<(), Error> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(Error::InternalError(format!(
+            "Failed to execute clipboard command `{}`",
+            command
+        )))
+    }
+}
+
+/// Ask the terminal to set the clipboard. This is done by the terminal the user is looking at,
+/// so it also works in a remote session
+fn copy_with_osc52(text: &str) -> Result<(), Error> {
+    let mut sequence = format!("\x1b]52;c;{}\x07", base64::encode(text.as_bytes()));
+
+    // tmux only passes the sequence on to the terminal when it is wrapped
+    if env::var_os("TMUX").is_some() {
+        sequence = format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"));
+    }
+
+    // Write to the terminal directly, the output may be redirected
+    let mut tty = OpenOptions::new()
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|_| {
+            Error::InternalError(
+                "No terminal to copy to, configure a clipboard command instead".to_string(),
+            )
+        })?;
+    tty.write_all(sequence.as_bytes())?;
+    Ok(())
+}
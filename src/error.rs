@@ -8,6 +8,7 @@ use std::string::FromUtf8Error;
 pub enum Error {
     FileError(io::Error),
     InternalError(String),
+    QueryError(String),
 }
 
 /// Implement display for error type
@@ -16,6 +17,7 @@ impl fmt::Display for Error {
         match *self {
             Error::FileError(ref e) => write!(f, "FileError: {}", e),
             Error::InternalError(ref s) => write!(f, "Internal error: {}", s),
+            Error::QueryError(ref s) => write!(f, "Invalid query: {}", s),
         }
     }
 }
@@ -26,6 +28,7 @@ impl StdError for Error {
         match *self {
             Error::FileError(ref e) => e.description(),
             Error::InternalError(ref _s) => "Internal processing error",
+            Error::QueryError(ref _s) => "Invalid search query",
         }
     }
 }
@@ -49,6 +52,18 @@ impl From<FromUtf8Error> for Error {
     }
 }
 
+impl From<serde_yaml::Error> for Error {
+    fn from(err: serde_yaml::Error) -> Error {
+        Error::InternalError(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::InternalError(err.to_string())
+    }
+}
+
 impl From<toml::de::Error> for Error {
     fn from(err: toml::de::Error) -> Error {
         Error::InternalError(err.to_string())
@@ -0,0 +1,128 @@
+use crate::error::Error;
+use crate::git;
+use crate::project::Project;
+use crate::snippet::Snippet;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path;
+use std::str::FromStr;
+
+/// Machine readable output formats for the found snippets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// A single array with all snippets
+    Json,
+    /// One object per line
+    Ndjson,
+    /// One tab separated line per snippet, without a header
+    Tsv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(format!(
+                "Unknown format `{}`, expected json, ndjson or tsv",
+                s
+            )),
+        }
+    }
+}
+
+/// The fields of a snippet that are written
+#[derive(Serialize, Debug)]
+struct Record<'a> {
+    /// The path of the file, also for a section
+    path: &'a str,
+    /// The snippet location the snippet is in
+    location: Option<&'a str>,
+    title: Option<&'a str>,
+    tags: &'a [String],
+    score: f64,
+    /// `None` when the location is not a git repo or git failed
+    git_status: Option<git::FileStatus>,
+    /// The anchor of the heading for a section of a split file
+    anchor: Option<&'a str>,
+}
+
+/// Write the snippets in the format, in the order they are given
+pub fn write_snippets<W: Write>(
+    out: &mut W,
+    snippets: &[Snippet],
+    project: &Project,
+    format: Format,
+) -> Result<(), Error> {
+    // Only ask git once per location, the status is left out when git fails, e.g. when the
+    // location is not a repo
+    let mut statuses = HashMap::new();
+    for location in project.locations.iter().filter(|l| l.git == Some(true)) {
+        if let Ok(changed) = git::determine_file_statuses(location) {
+            statuses.insert(location.local.as_str(), changed);
+        }
+    }
+
+    let records: Vec<Record> = snippets
+        .iter()
+        .map(|snippet| {
+            let path = path::Path::new(&snippet.name);
+            let location = project
+                .locations
+                .iter()
+                .find(|l| path.starts_with(&l.local))
+                .map(|l| l.local.as_str());
+            let git_status = location.and_then(|l| statuses.get(l)).map(|changed| {
+                path.canonicalize()
+                    .ok()
+                    .and_then(|p| changed.get(&p).cloned())
+                    .unwrap_or(git::FileStatus::Clean)
+            });
+            Record {
+                path: &snippet.name,
+                location,
+                title: snippet.title.as_deref(),
+                tags: &snippet.tags,
+                score: snippet.score,
+                git_status,
+                anchor: snippet.anchor.as_deref(),
+            }
+        })
+        .collect();
+
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, &records)?;
+            writeln!(out)?;
+        }
+        Format::Ndjson => {
+            for record in &records {
+                serde_json::to_writer(&mut *out, record)?;
+                writeln!(out)?;
+            }
+        }
+        Format::Tsv => {
+            for record in &records {
+                let fields = [
+                    record.path.to_string(),
+                    record.location.unwrap_or("").to_string(),
+                    record.title.unwrap_or("").to_string(),
+                    record.tags.join(","),
+                    format!("{:.3}", record.score),
+                    record.git_status.map_or("", |s| s.as_str()).to_string(),
+                    record.anchor.unwrap_or("").to_string(),
+                ];
+                // Tabs and newlines would break up the columns
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|f| f.replace(['\t', '\n', '\r'], " "))
+                    .collect();
+                writeln!(out, "{}", fields.join("\t"))?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use crate::error;
 use crate::project::{Project, SnippetLocation};
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::io::Error;
+use std::path::{Path, PathBuf};
 use std::process::Output;
 use std::process::{Command, Stdio};
 
@@ -73,6 +75,150 @@ pub fn determine_git_modified_status(
     })?
 }
 
+/// Git status of a single snippet file
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Clean,
+    Modified,
+    Added,
+    Untracked,
+}
+
+impl FileStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FileStatus::Clean => "clean",
+            FileStatus::Modified => "modified",
+            FileStatus::Added => "added",
+            FileStatus::Untracked => "untracked",
+        }
+    }
+}
+
+/// Determine the status of the changed files in the snippet location, keyed by their full path.
+/// Files that are not in the map are clean
+pub fn determine_file_statuses(
+    location: &SnippetLocation,
+) -> Result<HashMap<PathBuf, FileStatus>, error::Error> {
+    // The paths in the status are relative to the root of the repo
+    let output = run_git_command_for(location, ["rev-parse", "--show-toplevel"])?;
+    if !output.status.success() {
+        return Err(error::Error::InternalError(
+            "Failed to execute `rev-parse` command".to_string(),
+        ));
+    }
+    let root = PathBuf::from(String::from_utf8(output.stdout)?.trim_end());
+
+    let output = run_git_command_for(
+        location,
+        ["status", "--porcelain", "-z", "--untracked-files=all"],
+    )?;
+    if !output.status.success() {
+        return Err(error::Error::InternalError(
+            "Failed to execute `status` command".to_string(),
+        ));
+    }
+    Ok(parse_file_statuses(
+        &root,
+        &String::from_utf8(output.stdout)?,
+    ))
+}
+
+/// Parse the output of `git status --porcelain -z`, the paths are joined to the root of the repo
+fn parse_file_statuses(root: &Path, output: &str) -> HashMap<PathBuf, FileStatus> {
+    let mut statuses = HashMap::new();
+    let mut entries = output.split('\0');
+    while let Some(entry) = entries.next() {
+        // The output ends with a NUL
+        if entry.len() <= 3 {
+            continue;
+        }
+        let (code, path) = entry.split_at(3);
+        // A rename is followed by the original path, which is not an entry of its own
+        if code.contains(['R', 'C']) {
+            entries.next();
+        }
+        let status = match code.trim_end() {
+            "??" => FileStatus::Untracked,
+            c if c.starts_with('A') => FileStatus::Added,
+            _ => FileStatus::Modified,
+        };
+        statuses.insert(root.join(path), status);
+    }
+    statuses
+}
+
+/// Check if the file is tracked in the git repo of the snippet location
+pub fn git_is_tracked(location: &SnippetLocation, path: &Path) -> bool {
+    Command::new("git")
+        .current_dir(&location.local)
+        .args(["ls-files", "--error-unmatch", "--"])
+        .arg(path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Git mv a file in the snippet location
+pub fn git_mv(location: &SnippetLocation, from: &Path, to: &Path) -> Result<(), error::Error> {
+    let output = run_git_command_for(
+        location,
+        [
+            OsStr::new("mv"),
+            OsStr::new("--"),
+            from.as_os_str(),
+            to.as_os_str(),
+        ],
+    );
+
+    // Return if success
+    if output?.status.success() {
+        Ok(())
+    } else {
+        Err(error::Error::InternalError(
+            "Failed to execute `mv` command".to_string(),
+        ))
+    }
+}
+
+/// Git rm a file from the snippet location, only from the index when `cached` is set.
+/// Otherwise the file is also removed when it has staged changes, so confirm this first
+pub fn git_rm(location: &SnippetLocation, path: &Path, cached: bool) -> Result<(), error::Error> {
+    let mut args = vec![OsStr::new("rm"), OsStr::new("-q")];
+    args.push(OsStr::new(if cached { "--cached" } else { "-f" }));
+    args.push(OsStr::new("--"));
+    args.push(path.as_os_str());
+    let output = run_git_command_for(location, &args);
+
+    // Return if success
+    if output?.status.success() {
+        Ok(())
+    } else {
+        Err(error::Error::InternalError(
+            "Failed to execute `rm` command".to_string(),
+        ))
+    }
+}
+
+/// Git add a single file in the snippet location
+pub fn git_add_path(location: &SnippetLocation, path: &Path) -> Result<(), error::Error> {
+    let output = run_git_command_for(
+        location,
+        [OsStr::new("add"), OsStr::new("--"), path.as_os_str()],
+    );
+
+    // Return if success
+    if output?.status.success() {
+        Ok(())
+    } else {
+        Err(error::Error::InternalError(
+            "Failed to execute `add` command".to_string(),
+        ))
+    }
+}
+
 /// Sync/pull git location with upstream repo
 pub fn git_pull(location: &SnippetLocation) -> Result<(), error::Error> {
     let output = run_git_command_for(location, &["pull"]);
@@ -128,3 +274,50 @@ pub fn git_commit(location: &SnippetLocation, msg: String) -> Result<(), error::
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statuses(output: &str) -> Vec<(String, FileStatus)> {
+        let mut statuses: Vec<(String, FileStatus)> =
+            parse_file_statuses(Path::new("/repo"), output)
+                .into_iter()
+                .map(|(path, status)| (path.to_str().unwrap().to_string(), status))
+                .collect();
+        statuses.sort_by(|a, b| a.0.cmp(&b.0));
+        statuses
+    }
+
+    #[test]
+    fn parse_file_statuses_of_each_kind() {
+        assert_eq!(
+            statuses(" M a.md\0?? new.md\0A  added.md\0AM both.md\0 D gone.md\0"),
+            vec![
+                ("/repo/a.md".to_string(), FileStatus::Modified),
+                ("/repo/added.md".to_string(), FileStatus::Added),
+                ("/repo/both.md".to_string(), FileStatus::Added),
+                ("/repo/gone.md".to_string(), FileStatus::Modified),
+                ("/repo/new.md".to_string(), FileStatus::Untracked),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_file_statuses_skips_the_original_path_of_renames() {
+        assert_eq!(
+            statuses("R  new name.md\0old name.md\0C  copy.md\0a.md\0?? x.md\0"),
+            vec![
+                ("/repo/copy.md".to_string(), FileStatus::Modified),
+                ("/repo/new name.md".to_string(), FileStatus::Modified),
+                ("/repo/x.md".to_string(), FileStatus::Untracked),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_file_statuses_of_empty_output() {
+        assert!(statuses("").is_empty());
+        assert!(statuses("\0").is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::error;
+use crate::storage;
+use std::fs::File;
+use std::io::Read;
+use std::path;
+
+/// Number of queries that are remembered
+const MAX_ENTRIES: usize = 100;
+
+/// Queries entered in the picker, stored in the data dir of the user with the most recent last
+pub struct History {
+    entries: Vec<String>,
+    /// The entry that is recalled, `None` when editing a new query
+    position: Option<usize>,
+    /// The new query, kept while browsing the history
+    draft: String,
+}
+
+impl History {
+    /// An empty history
+    pub fn new() -> History {
+        History {
+            entries: Vec::new(),
+            position: None,
+            draft: String::new(),
+        }
+    }
+
+    /// Location of the history in the data dir of the user
+    fn location() -> Option<path::PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rusty-x").join("history"))
+    }
+
+    /// Load the history from the data dir, an empty history is returned when it cannot be read
+    pub fn load() -> History {
+        let mut history = History::new();
+        if let Some(path) = History::location() {
+            let mut buffer = String::new();
+            if File::open(path)
+                .and_then(|mut f| f.read_to_string(&mut buffer))
+                .is_ok()
+            {
+                history.entries = buffer
+                    .lines()
+                    .filter(|l| !l.is_empty())
+                    .map(|l| l.to_string())
+                    .collect();
+            }
+        }
+        history
+    }
+
+    /// Write the history to the data dir
+    pub fn save(&self) -> Result<(), error::Error> {
+        let path = match History::location() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let mut to_write = self.entries.join("\n");
+        to_write.push('\n');
+        storage::save_file(&path, &to_write)
+    }
+
+    /// Remember the query as the most recent one, an earlier occurrence is removed
+    pub fn add(&mut self, query: &str) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+        self.entries.retain(|e| e != query);
+        self.entries.push(query.to_string());
+        if self.entries.len() > MAX_ENTRIES {
+            let excess = self.entries.len() - MAX_ENTRIES;
+            self.entries.drain(..excess);
+        }
+        self.position = None;
+    }
+
+    /// Recall the query before the recalled one, `current` is kept to return to
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            Some(0) => return None,
+            Some(position) => position - 1,
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// Recall the query after the recalled one, or the new query when past the most recent
+    pub fn next(&mut self) -> Option<&str> {
+        let position = self.position?;
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            Some(&self.entries[position + 1])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+}
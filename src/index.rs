@@ -0,0 +1,220 @@
+use crate::error;
+use crate::snippet::{Heading, Metadata};
+use crate::storage;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Bump this when the cached data changes, so old indices are rebuilt
+const INDEX_VERSION: u32 = 4;
+
+/// Metadata of a snippet file, valid as long as the file has the same modification time and size
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    modified: u64,
+    modified_nanos: u32,
+    size: u64,
+    /// Only read for files that are split in sections. This has to come before the metadata,
+    /// TOML can not have an empty list after a table
+    #[serde(default)]
+    headings: Option<Vec<Heading>>,
+    metadata: Metadata,
+}
+
+/// Persistent index of the snippet metadata, so unchanged files don't need to be parsed again
+#[derive(Serialize, Deserialize)]
+pub struct Index {
+    version: u32,
+    entries: HashMap<String, IndexEntry>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl Index {
+    /// An empty index
+    pub fn new() -> Index {
+        Index {
+            version: INDEX_VERSION,
+            entries: HashMap::new(),
+            dirty: true,
+        }
+    }
+
+    /// Location of the index in the cache dir of the user
+    fn location() -> Option<path::PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("rusty-x").join("index.toml"))
+    }
+
+    /// Load the index from the cache dir, an empty index is returned when there is no valid index
+    pub fn load() -> Index {
+        Index::location()
+            .and_then(|path| Index::read(&path).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_else(Index::new)
+    }
+
+    fn read(path: &path::Path) -> Result<Index, error::Error> {
+        let mut buffer = String::new();
+        File::open(path)?.read_to_string(&mut buffer)?;
+        Ok(toml::from_str(&buffer)?)
+    }
+
+    fn write(&self, path: &path::Path) -> Result<(), error::Error> {
+        storage::save_file(path, &toml::to_string(self)?)
+    }
+
+    /// Write the index to the cache dir, if it has been changed
+    pub fn save(&self) -> Result<(), error::Error> {
+        let path = match Index::location() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+
+        self.write(&path)
+    }
+
+    /// Get the metadata of the file, only parses the file when it changed since it was indexed
+    pub fn metadata(
+        &mut self,
+        file_path: &str,
+        modified: SystemTime,
+        size: u64,
+        read: impl FnOnce(&str) -> Result<Metadata, error::Error>,
+    ) -> Result<Metadata, error::Error> {
+        let since_epoch = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let (secs, nanos) = (since_epoch.as_secs(), since_epoch.subsec_nanos());
+
+        if let Some(entry) = self.entries.get(file_path) {
+            if entry.modified == secs && entry.modified_nanos == nanos && entry.size == size {
+                return Ok(entry.metadata.clone());
+            }
+        }
+
+        let metadata = read(file_path)?;
+        self.entries.insert(
+            file_path.to_string(),
+            IndexEntry {
+                modified: secs,
+                modified_nanos: nanos,
+                size,
+                metadata: metadata.clone(),
+                headings: None,
+            },
+        );
+        self.dirty = true;
+        Ok(metadata)
+    }
+
+    /// Get the headings of the file, only parses the file when it changed since the headings
+    /// were indexed. The metadata of the file has to be indexed first
+    pub fn headings(
+        &mut self,
+        file_path: &str,
+        modified: SystemTime,
+        size: u64,
+        read: impl FnOnce(&str) -> Result<Vec<Heading>, error::Error>,
+    ) -> Result<Vec<Heading>, error::Error> {
+        let since_epoch = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let (secs, nanos) = (since_epoch.as_secs(), since_epoch.subsec_nanos());
+
+        let entry = self
+            .entries
+            .get_mut(file_path)
+            .filter(|e| e.modified == secs && e.modified_nanos == nanos && e.size == size);
+        match entry {
+            Some(entry) => {
+                if let Some(ref headings) = entry.headings {
+                    return Ok(headings.clone());
+                }
+                let headings = read(file_path)?;
+                entry.headings = Some(headings.clone());
+                self.dirty = true;
+                Ok(headings)
+            }
+            None => read(file_path),
+        }
+    }
+
+    /// Remove the entries of files that no longer exist
+    pub fn retain(&mut self, paths: &HashSet<&str>) {
+        let before = self.entries.len();
+        self.entries.retain(|path, _| paths.contains(path.as_str()));
+        if self.entries.len() != before {
+            self.dirty = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heading(title: &str) -> Heading {
+        Heading {
+            line: 2,
+            level: 1,
+            title: title.to_string(),
+            anchor: title.to_lowercase(),
+            tags: vec!["tag".to_string()],
+            description: None,
+            code_languages: vec!["bash".to_string()],
+        }
+    }
+
+    #[test]
+    fn write_and_read_with_empty_and_other_headings() {
+        let modified = UNIX_EPOCH + std::time::Duration::new(1_500_000_000, 42);
+        let files: [(&str, Option<Vec<Heading>>); 3] = [
+            ("/snippets/plain.md", None),
+            ("/snippets/empty.md", Some(vec![])),
+            (
+                "/snippets/split.md",
+                Some(vec![heading("One"), heading("Two")]),
+            ),
+        ];
+        let mut index = Index::new();
+        for (file, headings) in &files {
+            let metadata = Metadata {
+                title: Some(file.to_string()),
+                tags: vec!["a".to_string(), "b".to_string()],
+                ..Metadata::default()
+            };
+            index
+                .metadata(file, modified, 10, |_| Ok(metadata))
+                .unwrap();
+            if let Some(headings) = headings {
+                index
+                    .headings(file, modified, 10, |_| Ok(headings.clone()))
+                    .unwrap();
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("rusty-x-test-{}.toml", std::process::id()));
+        index.write(&path).unwrap();
+        let read = Index::read(&path);
+        std::fs::remove_file(&path).unwrap();
+        let mut read = read.unwrap();
+
+        assert_eq!(read.version, INDEX_VERSION);
+        for (file, headings) in &files {
+            let metadata = read
+                .metadata(file, modified, 10, |_| panic!("{} is not indexed", file))
+                .unwrap();
+            assert_eq!(metadata.title.as_deref(), Some(*file));
+            assert_eq!(metadata.tags, vec!["a", "b"]);
+            if let Some(headings) = headings {
+                let read_headings = read
+                    .headings(file, modified, 10, |_| panic!("{} has no headings", file))
+                    .unwrap();
+                let titles: Vec<&str> = read_headings.iter().map(|h| h.title.as_str()).collect();
+                let expected: Vec<&str> = headings.iter().map(|h| h.title.as_str()).collect();
+                assert_eq!(titles, expected);
+            }
+        }
+    }
+}
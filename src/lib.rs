@@ -2,13 +2,37 @@
 extern crate serde_derive;
 
 mod x;
-pub use x::{edit_snippet, start_operation, OpCode};
+pub use x::{
+    delete_snippet, edit_snippet, expand_aliases, move_snippet, rename_snippet, run_code,
+    start_operation, OpCode,
+};
 
 mod project;
-pub use project::{Project, ProjectOperation};
+pub use project::{
+    ClipboardConfig, KeyConfig, PickerConfig, Project, ProjectOperation, RunConfig, SearchConfig,
+    ThemeConfig,
+};
 
 mod snippet;
-pub use snippet::Snippet;
+pub use snippet::{
+    fill_placeholders, find_placeholders, locate, read_body, read_code_blocks, read_content,
+    read_headings, BodyMatch, CodeBlock, Heading, Metadata, Placeholder, Snippet,
+};
+
+mod storage;
+
+mod index;
+
+mod history;
+
+mod recent;
+pub use recent::RecentValues;
+
+mod query;
+pub use query::{Field, Query};
+
+mod search;
+pub use search::{SearchOptions, Strictness};
 
 mod error;
 pub use error::Error;
@@ -16,5 +40,17 @@ pub use error::Error;
 mod git;
 pub use git::*;
 
+mod format;
+pub use format::{write_snippets, Format};
+
+mod clipboard;
+pub use clipboard::copy_to_clipboard;
+
+mod theme;
+pub use theme::{ColorMode, Theme};
+
 mod term_select;
-pub use term_select::show_multiple_results;
\ No newline at end of file
+pub use term_select::{
+    show_multiple_results, show_results_with_preview, Action, PickerOptions, PreviewPosition,
+    Selection,
+};
@@ -1,5 +1,9 @@
 use crate::error;
 use crate::git;
+use crate::search::Strictness;
+use crate::term_select::PreviewPosition;
+use crate::theme::ColorMode;
+use std::collections::BTreeMap;
 use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
@@ -11,6 +15,13 @@ pub struct SnippetLocation {
     pub local: String,
     pub ext: String,
     pub git: Option<bool>,
+    /// How many levels of subfolders are searched for snippets, unlimited when not set
+    pub max_depth: Option<usize>,
+    /// Descend into symlinked folders, defaults to false
+    pub follow_symlinks: Option<bool>,
+    /// Use the sections under the headings of this level, e.g. 2 for `##`, as separate
+    /// snippets. Files without such headings are a single snippet
+    pub split_level: Option<usize>,
 }
 
 impl SnippetLocation {
@@ -19,6 +30,9 @@ impl SnippetLocation {
             local: String::from(home.to_owned() + "/.snippets"),
             ext: "md".to_string(),
             git: None,
+            max_depth: None,
+            follow_symlinks: None,
+            split_level: None,
         };
     }
 
@@ -32,10 +46,192 @@ impl SnippetLocation {
     }
 }
 
+/// Configuration of the snippet search
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SearchConfig {
+    /// Weight of a keyword found in the title
+    pub title_boost: f64,
+    /// Weight of a keyword found in the tags
+    pub tags_boost: f64,
+    /// Weight of a keyword found in the body
+    pub body_boost: f64,
+    /// Combine keywords with AND instead of OR when no operator is given
+    pub match_all: bool,
+    /// How close a word has to be to a keyword to match
+    pub strictness: Strictness,
+}
+
+impl Default for SearchConfig {
+    fn default() -> SearchConfig {
+        SearchConfig {
+            title_boost: 2.0,
+            tags_boost: 3.0,
+            body_boost: 1.0,
+            match_all: false,
+            strictness: Strictness::default(),
+        }
+    }
+}
+
+/// Keys for the actions in the snippet picker, e.g. `enter`, `ctrl-e` or `alt-d`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct KeyConfig {
+    pub view: String,
+    pub edit: String,
+    pub copy: String,
+    pub delete: String,
+    pub run: String,
+    /// Show or hide the preview
+    pub toggle_preview: String,
+}
+
+impl Default for KeyConfig {
+    fn default() -> KeyConfig {
+        KeyConfig {
+            view: "enter".to_string(),
+            edit: "ctrl-e".to_string(),
+            copy: "ctrl-y".to_string(),
+            delete: "alt-d".to_string(),
+            run: "ctrl-r".to_string(),
+            toggle_preview: "ctrl-t".to_string(),
+        }
+    }
+}
+
+/// Configuration of the snippet picker
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PickerConfig {
+    /// Show the preview of the highlighted snippet when the picker opens
+    pub preview: bool,
+    /// Where the preview is shown, `right` or `bottom`
+    pub preview_position: PreviewPosition,
+    /// Show the fuzzy match score of the results
+    pub show_score: bool,
+    /// Show the picker below the cursor with this height, e.g. `15` rows or `40%` of the
+    /// terminal, instead of on the whole screen
+    pub height: Option<String>,
+}
+
+impl Default for PickerConfig {
+    fn default() -> PickerConfig {
+        PickerConfig {
+            preview: true,
+            preview_position: PreviewPosition::default(),
+            show_score: false,
+            height: None,
+        }
+    }
+}
+
+/// Colors of the picker and the output, like `red`, `bright-blue`, `238` or `#ff8800`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// Use colors: `auto` (when writing to a terminal), `always` or `never`
+    pub color: ColorMode,
+    pub prompt: String,
+    /// The query typed in the picker
+    pub query: String,
+    /// Characters that match the query
+    pub matched: String,
+    /// Background of the highlighted result
+    pub highlight: String,
+    /// Marker of the selected results
+    pub selected: String,
+    /// Snippet locations with modified files
+    pub modified: String,
+    /// Name of the syntax highlighting theme for viewing and previewing snippets
+    pub syntax_theme: Option<String>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> ThemeConfig {
+        ThemeConfig {
+            color: ColorMode::default(),
+            prompt: "blue".to_string(),
+            query: "white".to_string(),
+            matched: "yellow".to_string(),
+            highlight: "238".to_string(),
+            selected: "green".to_string(),
+            modified: "yellow".to_string(),
+            syntax_theme: None,
+        }
+    }
+}
+
+/// How snippets are copied to the clipboard
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ClipboardConfig {
+    /// Command that gets the text on stdin, like `pbcopy` or `xclip -selection clipboard`. An
+    /// OSC 52 escape sequence is written to the terminal when not set, which also works over SSH
+    pub command: Option<String>,
+}
+
+/// How code blocks are run with `--run`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct RunConfig {
+    /// Command for the language of a code block, the code is passed to it in a file. These are
+    /// used before the default interpreters
+    pub interpreters: BTreeMap<String, String>,
+}
+
+impl RunConfig {
+    /// The command to run code in the language with, `sh` for code without a language
+    pub fn interpreter(&self, language: Option<&str>) -> Option<String> {
+        let language = language.unwrap_or("sh").to_lowercase();
+        if let Some(interpreter) = self.interpreters.get(&language) {
+            return Some(interpreter.to_string());
+        }
+        let interpreter = match language.as_str() {
+            "sh" | "shell" => "sh",
+            "bash" => "bash",
+            "zsh" => "zsh",
+            "fish" => "fish",
+            "python" | "python3" | "py" => "python3",
+            "ruby" | "rb" => "ruby",
+            "javascript" | "js" | "node" => "node",
+            "perl" => "perl",
+            "powershell" | "pwsh" | "ps1" => "pwsh",
+            _ => return None,
+        };
+        Some(interpreter.to_string())
+    }
+
+    /// The extension of the file the code in the language is written to, some interpreters like
+    /// `pwsh` only run files with their own extension. Other languages use their name
+    pub fn extension(language: Option<&str>) -> String {
+        let language = language.unwrap_or("sh").to_lowercase();
+        let extension = match language.as_str() {
+            "sh" | "shell" | "bash" | "zsh" => "sh",
+            "python" | "python3" | "py" => "py",
+            "ruby" | "rb" => "rb",
+            "javascript" | "js" | "node" => "js",
+            "perl" => "pl",
+            "powershell" | "pwsh" | "ps1" => "ps1",
+            _ => return language,
+        };
+        extension.to_string()
+    }
+}
+
 /// Project folder structure
 #[derive(Serialize, Deserialize)]
 pub struct Project {
     pub locations: Vec<SnippetLocation>,
+    pub search: Option<SearchConfig>,
+    pub keys: Option<KeyConfig>,
+    pub picker: Option<PickerConfig>,
+    pub theme: Option<ThemeConfig>,
+    pub clipboard: Option<ClipboardConfig>,
+    pub run: Option<RunConfig>,
+    /// Synonyms mapped to the canonical tag they stand for
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
 }
 
 pub enum ProjectOperation {
@@ -44,6 +240,70 @@ pub enum ProjectOperation {
 }
 
 impl Project {
+    /// The search configuration, or the defaults if not configured
+    pub fn search_config(&self) -> SearchConfig {
+        self.search.clone().unwrap_or_default()
+    }
+
+    /// The picker keys, or the defaults if not configured
+    pub fn key_config(&self) -> KeyConfig {
+        self.keys.clone().unwrap_or_default()
+    }
+
+    /// The picker settings, or the defaults if not configured
+    pub fn picker_config(&self) -> PickerConfig {
+        self.picker.clone().unwrap_or_default()
+    }
+
+    /// The colors, or the defaults if not configured
+    pub fn theme_config(&self) -> ThemeConfig {
+        self.theme.clone().unwrap_or_default()
+    }
+
+    /// The clipboard settings, or the defaults if not configured
+    pub fn clipboard_config(&self) -> ClipboardConfig {
+        self.clipboard.clone().unwrap_or_default()
+    }
+
+    /// The interpreters for running code, or the defaults if not configured
+    pub fn run_config(&self) -> RunConfig {
+        self.run.clone().unwrap_or_default()
+    }
+
+    /// The canonical tag for a synonym, or the tag itself if it is not an alias
+    pub fn canonical_tag(&self, tag: &str) -> String {
+        let lower = tag.to_lowercase();
+        self.aliases
+            .iter()
+            .find(|(alias, _)| alias.to_lowercase() == lower)
+            .map_or(tag.to_string(), |(_, canonical)| canonical.to_string())
+    }
+
+    /// Replace the synonyms in the tags by their canonical tag
+    pub fn canonical_tags(&self, tags: &[String]) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+        for tag in tags.iter().map(|t| self.canonical_tag(t)) {
+            if !result.contains(&tag) {
+                result.push(tag);
+            }
+        }
+        result
+    }
+
+    /// All words that mean the same as the tag, starting with the canonical tag
+    pub fn synonyms(&self, tag: &str) -> Vec<String> {
+        let canonical = self.canonical_tag(tag);
+        let lower = canonical.to_lowercase();
+        let mut synonyms = vec![canonical];
+        synonyms.extend(
+            self.aliases
+                .iter()
+                .filter(|(_, c)| c.to_lowercase() == lower)
+                .map(|(alias, _)| alias.to_string()),
+        );
+        synonyms
+    }
+
     /// Write a project
     pub fn write(&self, folder: &path::Path) -> Result<(), error::Error> {
         let to_write = toml::to_string(self).expect("Cannot serialize project");
@@ -79,6 +339,13 @@ impl Project {
         } else {
             ProjectOperation::NotExist(Project {
                 locations: vec![SnippetLocation::default(&home)],
+                search: None,
+                keys: None,
+                picker: None,
+                theme: None,
+                clipboard: None,
+                run: None,
+                aliases: BTreeMap::new(),
             })
         };
 
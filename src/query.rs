@@ -0,0 +1,511 @@
+use crate::error;
+
+/// The field a query term is matched against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    /// Title, tags and, when searched, the body
+    Any,
+    Tag,
+    Title,
+    Lang,
+    Path,
+}
+
+impl Field {
+    fn from_qualifier(qualifier: &str) -> Option<Field> {
+        match qualifier {
+            "tag" => Some(Field::Tag),
+            "title" => Some(Field::Title),
+            "lang" => Some(Field::Lang),
+            "path" => Some(Field::Path),
+            _ => None,
+        }
+    }
+}
+
+/// Parsed search query
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// Matches every snippet
+    All,
+    /// A single word
+    Term(Field, String),
+    /// Words that should occur in this order
+    Phrase(Field, String),
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Minus,
+    Open,
+    Close,
+}
+
+/// Split the query in tokens
+fn lex(input: &str) -> Result<Vec<Token>, error::Error> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                let mut phrase = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => phrase.push(c),
+                        None => {
+                            return Err(error::Error::QueryError(
+                                "Missing closing quote".to_string(),
+                            ))
+                        }
+                    }
+                }
+                tokens.push(Token::Phrase(phrase));
+            }
+            _ => {
+                // A leading minus excludes what follows
+                if c == '-' {
+                    chars.next();
+                    tokens.push(Token::Minus);
+                    continue;
+                }
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the tokens
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    match_all: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn is_operator(&self, operator: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) => w == operator,
+            _ => false,
+        }
+    }
+
+    /// or := and ("OR" and)*
+    fn parse_or(&mut self) -> Result<Query, error::Error> {
+        let mut alternatives = vec![self.parse_and()?];
+        while self.is_operator("OR") {
+            self.next();
+            alternatives.push(self.parse_and()?);
+        }
+        Ok(combine(alternatives, Query::Or))
+    }
+
+    /// and := unary (["AND"] unary)*
+    ///
+    /// Terms without an operator between them are combined with AND or OR, depending on the
+    /// option. Exclusions always have to hold.
+    fn parse_and(&mut self) -> Result<Query, error::Error> {
+        let mut required = Vec::new();
+        let mut implicit = Vec::new();
+        let mut explicit_and = false;
+
+        loop {
+            match self.peek() {
+                None | Some(Token::Close) => break,
+                Some(Token::Word(w)) if w == "OR" => break,
+                Some(Token::Word(w)) if w == "AND" => {
+                    self.next();
+                    explicit_and = true;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let query = self.parse_unary()?;
+            if let Query::Not(_) = query {
+                required.push(query);
+            } else if explicit_and && !implicit.is_empty() {
+                // Bind to the previous term: `a AND b`
+                let previous = match implicit.pop() {
+                    Some(Query::And(mut queries)) => {
+                        queries.push(query);
+                        queries
+                    }
+                    Some(previous) => vec![previous, query],
+                    None => vec![query],
+                };
+                implicit.push(Query::And(previous));
+            } else {
+                implicit.push(query);
+            }
+            explicit_and = false;
+        }
+
+        if explicit_and {
+            return Err(error::Error::QueryError(
+                "Expected a term after AND".to_string(),
+            ));
+        }
+
+        if self.match_all {
+            required.extend(implicit);
+        } else if !implicit.is_empty() {
+            required.push(combine(implicit, Query::Or));
+        }
+        Ok(combine(required, Query::And))
+    }
+
+    /// unary := ("-" | "NOT") unary | primary
+    fn parse_unary(&mut self) -> Result<Query, error::Error> {
+        if self.peek() == Some(&Token::Minus) || self.is_operator("NOT") {
+            self.next();
+            return Ok(Query::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    /// primary := "(" or ")" | [field ":"] (word | phrase)
+    fn parse_primary(&mut self) -> Result<Query, error::Error> {
+        match self.next() {
+            Some(Token::Open) => {
+                let query = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(query),
+                    _ => Err(error::Error::QueryError(
+                        "Missing closing parenthesis".to_string(),
+                    )),
+                }
+            }
+            Some(Token::Phrase(phrase)) => Ok(Query::Phrase(Field::Any, phrase)),
+            Some(Token::Word(word)) => {
+                // Check for a field qualifier
+                if let Some(colon) = word.find(':') {
+                    if let Some(field) = Field::from_qualifier(&word[..colon]) {
+                        let value = &word[colon + 1..];
+                        if !value.is_empty() {
+                            return Ok(Query::Term(field, value.to_string()));
+                        }
+                        return match self.next() {
+                            Some(Token::Phrase(phrase)) => Ok(Query::Phrase(field, phrase)),
+                            Some(Token::Word(value)) => Ok(Query::Term(field, value)),
+                            _ => Err(error::Error::QueryError(format!(
+                                "Expected a value after `{}`",
+                                word
+                            ))),
+                        };
+                    }
+                }
+                Ok(Query::Term(Field::Any, word))
+            }
+            Some(Token::Close) => Err(error::Error::QueryError(
+                "Unexpected closing parenthesis".to_string(),
+            )),
+            Some(Token::Minus) | None => Err(error::Error::QueryError(
+                "Expected a search term".to_string(),
+            )),
+        }
+    }
+}
+
+/// Combine the queries, without nesting a single query
+fn combine(mut queries: Vec<Query>, operator: fn(Vec<Query>) -> Query) -> Query {
+    match queries.len() {
+        0 => Query::All,
+        1 => queries.remove(0),
+        _ => operator(queries),
+    }
+}
+
+impl Query {
+    /// Parse the keywords into a query. Keywords containing whitespace are treated as a phrase,
+    /// terms are combined with AND when `match_all` is set and with OR otherwise
+    pub fn parse(keywords: &[String], match_all: bool) -> Result<Query, error::Error> {
+        let input: Vec<String> = keywords
+            .iter()
+            .map(|k| {
+                if k.contains(char::is_whitespace) && !k.contains('"') {
+                    format!("\"{}\"", k)
+                } else {
+                    k.to_string()
+                }
+            })
+            .collect();
+
+        let mut parser = Parser {
+            tokens: lex(&input.join(" "))?,
+            position: 0,
+            match_all,
+        };
+        let query = parser.parse_or()?;
+        if parser.position < parser.tokens.len() {
+            return Err(error::Error::QueryError(
+                "Unexpected closing parenthesis".to_string(),
+            ));
+        }
+        Ok(query)
+    }
+
+    /// The terms that should be found, for ranking. Phrases are split into words
+    pub fn positive_terms(&self) -> Vec<(Field, String)> {
+        match self {
+            Query::All | Query::Not(_) => Vec::new(),
+            Query::Term(field, term) => vec![(*field, term.to_string())],
+            Query::Phrase(field, phrase) => phrase
+                .split_whitespace()
+                .map(|w| (*field, w.to_string()))
+                .collect(),
+            Query::And(queries) | Query::Or(queries) => {
+                queries.iter().flat_map(|q| q.positive_terms()).collect()
+            }
+        }
+    }
+
+    /// Replace terms that have synonyms by a query for any of the synonyms. The terms that were
+    /// expanded are added to `expanded`, together with the synonyms used
+    pub fn expand<F>(&self, synonyms: &F, expanded: &mut Vec<(String, Vec<String>)>) -> Query
+    where
+        F: Fn(&str) -> Vec<String>,
+    {
+        match self {
+            Query::Term(field, term) if *field == Field::Any || *field == Field::Tag => {
+                let mut words = synonyms(term);
+                words.retain(|w| !w.eq_ignore_ascii_case(term));
+                if words.is_empty() {
+                    return self.clone();
+                }
+                expanded.push((term.to_string(), words.clone()));
+
+                let mut alternatives = vec![Query::Term(*field, term.to_string())];
+                alternatives.extend(words.into_iter().map(|w| Query::Term(*field, w)));
+                Query::Or(alternatives)
+            }
+            Query::Not(query) => Query::Not(Box::new(query.expand(synonyms, expanded))),
+            Query::And(queries) => Query::And(
+                queries
+                    .iter()
+                    .map(|q| q.expand(synonyms, expanded))
+                    .collect(),
+            ),
+            Query::Or(queries) => Query::Or(
+                queries
+                    .iter()
+                    .map(|q| q.expand(synonyms, expanded))
+                    .collect(),
+            ),
+            _ => self.clone(),
+        }
+    }
+
+    /// Evaluate the query, using the function to match a single term or phrase
+    pub fn matches<F>(&self, matches_term: &F) -> bool
+    where
+        F: Fn(Field, &str, bool) -> bool,
+    {
+        match self {
+            Query::All => true,
+            Query::Term(field, term) => matches_term(*field, term, false),
+            Query::Phrase(field, phrase) => matches_term(*field, phrase, true),
+            Query::Not(query) => !query.matches(matches_term),
+            Query::And(queries) => queries.iter().all(|q| q.matches(matches_term)),
+            Query::Or(queries) => queries.iter().any(|q| q.matches(matches_term)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str, match_all: bool) -> Result<Query, error::Error> {
+        let keywords: Vec<String> = input.split(' ').map(|k| k.to_string()).collect();
+        Query::parse(&keywords, match_all)
+    }
+
+    fn term(word: &str) -> Query {
+        Query::Term(Field::Any, word.to_string())
+    }
+
+    fn not(query: Query) -> Query {
+        Query::Not(Box::new(query))
+    }
+
+    #[test]
+    fn lex_splits_words_phrases_and_parentheses() {
+        let tokens = lex(r#"(a "b c")-d e-f"#).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Open,
+                Token::Word("a".to_string()),
+                Token::Phrase("b c".to_string()),
+                Token::Close,
+                Token::Minus,
+                Token::Word("d".to_string()),
+                Token::Word("e-f".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_fails_on_missing_quote() {
+        assert!(lex(r#"a "b c"#).is_err());
+    }
+
+    #[test]
+    fn implicit_terms_use_the_match_option() {
+        assert_eq!(
+            parse("a b", false).unwrap(),
+            Query::Or(vec![term("a"), term("b")])
+        );
+        assert_eq!(
+            parse("a b", true).unwrap(),
+            Query::And(vec![term("a"), term("b")])
+        );
+    }
+
+    #[test]
+    fn and_binds_stronger_than_or() {
+        assert_eq!(
+            parse("a AND b OR c", false).unwrap(),
+            Query::Or(vec![Query::And(vec![term("a"), term("b")]), term("c")])
+        );
+        assert_eq!(
+            parse("a b AND c", false).unwrap(),
+            Query::Or(vec![term("a"), Query::And(vec![term("b"), term("c")])])
+        );
+    }
+
+    #[test]
+    fn minus_and_not_exclude() {
+        let expected = Query::And(vec![not(term("b")), term("a")]);
+        assert_eq!(parse("a -b", false).unwrap(), expected);
+        assert_eq!(parse("a NOT b", false).unwrap(), expected);
+        // Only a leading minus excludes
+        assert_eq!(parse("a-b", false).unwrap(), term("a-b"));
+        assert!(parse("a -", false).is_err());
+    }
+
+    #[test]
+    fn parentheses_group() {
+        assert_eq!(
+            parse("(a OR b) AND c", false).unwrap(),
+            Query::And(vec![Query::Or(vec![term("a"), term("b")]), term("c")])
+        );
+        assert_eq!(
+            parse("-(a b)", true).unwrap(),
+            not(Query::And(vec![term("a"), term("b")]))
+        );
+    }
+
+    #[test]
+    fn unbalanced_parentheses_fail() {
+        assert!(parse("(a b", false).is_err());
+        assert!(parse("a b)", false).is_err());
+        assert!(parse(")", false).is_err());
+        assert!(parse("()", false).is_ok());
+    }
+
+    #[test]
+    fn dangling_and_fails() {
+        assert!(parse("a AND", false).is_err());
+    }
+
+    #[test]
+    fn fields_qualify_words_and_phrases() {
+        assert_eq!(
+            parse("tag:docker", false).unwrap(),
+            Query::Term(Field::Tag, "docker".to_string())
+        );
+        assert_eq!(
+            parse(r#"title:"system prune""#, false).unwrap(),
+            Query::Phrase(Field::Title, "system prune".to_string())
+        );
+        // An unknown qualifier is part of the word
+        assert_eq!(parse("http://x", false).unwrap(), term("http://x"));
+        assert!(parse("lang:", false).is_err());
+    }
+
+    #[test]
+    fn keywords_with_whitespace_are_phrases() {
+        let keywords = vec!["docker compose".to_string()];
+        assert_eq!(
+            Query::parse(&keywords, false).unwrap(),
+            Query::Phrase(Field::Any, "docker compose".to_string())
+        );
+    }
+
+    #[test]
+    fn expand_replaces_terms_with_synonyms() {
+        let synonyms = |term: &str| -> Vec<String> {
+            if term == "k8s" {
+                vec!["k8s".to_string(), "kubernetes".to_string()]
+            } else {
+                Vec::new()
+            }
+        };
+        let mut expanded = Vec::new();
+        let query = parse("k8s -title:k8s", false)
+            .unwrap()
+            .expand(&synonyms, &mut expanded);
+        assert_eq!(
+            query,
+            Query::And(vec![
+                not(Query::Term(Field::Title, "k8s".to_string())),
+                Query::Or(vec![term("k8s"), term("kubernetes")]),
+            ])
+        );
+        assert_eq!(
+            expanded,
+            vec![("k8s".to_string(), vec!["kubernetes".to_string()])]
+        );
+    }
+
+    #[test]
+    fn matches_evaluates_the_query() {
+        let query = parse("(a OR b) -c", false).unwrap();
+        let words = |present: &'static [&'static str]| {
+            move |_: Field, term: &str, _: bool| present.contains(&term)
+        };
+        assert!(query.matches(&words(&["a"])));
+        assert!(query.matches(&words(&["b"])));
+        assert!(!query.matches(&words(&["a", "c"])));
+        assert!(!query.matches(&words(&[])));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::error;
+use crate::storage;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path;
+
+/// Number of values that are remembered per placeholder
+const MAX_VALUES: usize = 10;
+
+/// Values filled in for placeholders, stored in the data dir of the user with the most recent
+/// first
+pub struct RecentValues {
+    values: BTreeMap<String, Vec<String>>,
+}
+
+impl RecentValues {
+    /// Location of the values in the data dir of the user
+    fn location() -> Option<path::PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rusty-x").join("values.toml"))
+    }
+
+    /// Load the values from the data dir, no values are returned when they cannot be read
+    pub fn load() -> RecentValues {
+        let mut values = BTreeMap::new();
+        if let Some(path) = RecentValues::location() {
+            let mut buffer = String::new();
+            if File::open(path)
+                .and_then(|mut f| f.read_to_string(&mut buffer))
+                .is_ok()
+            {
+                values = toml::from_str(&buffer).unwrap_or_default();
+            }
+        }
+        RecentValues { values }
+    }
+
+    /// Write the values to the data dir
+    pub fn save(&self) -> Result<(), error::Error> {
+        let path = match RecentValues::location() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        storage::save_file(&path, &toml::to_string(&self.values)?)
+    }
+
+    /// The values used for the placeholder, most recent first
+    pub fn get(&self, name: &str) -> &[String] {
+        self.values.get(name).map_or(&[], |v| v.as_slice())
+    }
+
+    /// Remember the value as the most recent one for the placeholder
+    pub fn add(&mut self, name: &str, value: &str) {
+        if value.is_empty() {
+            return;
+        }
+        let values = self.values.entry(name.to_string()).or_default();
+        values.retain(|v| v != value);
+        values.insert(0, value.to_string());
+        values.truncate(MAX_VALUES);
+    }
+}
@@ -0,0 +1,526 @@
+use crate::project::SearchConfig;
+use crate::query::{Field, Query};
+use crate::snippet;
+use std::cmp::Ordering;
+use std::str::FromStr;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// BM25 term frequency saturation
+const K1: f64 = 1.2;
+/// BM25 field length normalization
+const B: f64 = 0.75;
+
+/// Options that change how snippets are searched
+#[derive(Debug, Default, Clone)]
+pub struct SearchOptions {
+    /// Also match the keywords against the markdown body of the snippets
+    pub body: bool,
+    /// Combine keywords with AND instead of OR when no operator is given
+    pub match_all: bool,
+    /// How close a word has to be to a keyword to match
+    pub strictness: Strictness,
+}
+
+/// How close a word has to be to a keyword to match, every level also accepts the matches
+/// of the levels above it
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Strictness {
+    /// The whole word, with the same case
+    Exact,
+    /// The whole word, ignoring case and accents
+    Word,
+    /// Words that start with or contain the keyword
+    #[default]
+    Prefix,
+    /// Words with a small typo as well
+    Fuzzy,
+}
+
+impl FromStr for Strictness {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Strictness, String> {
+        match s {
+            "exact" => Ok(Strictness::Exact),
+            "word" => Ok(Strictness::Word),
+            "prefix" => Ok(Strictness::Prefix),
+            "fuzzy" => Ok(Strictness::Fuzzy),
+            _ => Err(format!(
+                "Unknown match mode `{}`, expected exact, word, prefix or fuzzy",
+                s
+            )),
+        }
+    }
+}
+
+impl Strictness {
+    /// Normalize the text for comparison, only the exact mode keeps the case and accents
+    pub fn normalize(self, text: &str) -> String {
+        match self {
+            Strictness::Exact => text.to_string(),
+            _ => text
+                .nfkd()
+                .filter(|c| !is_combining_mark(*c))
+                .collect::<String>()
+                .to_lowercase(),
+        }
+    }
+
+    /// How well a normalized token matches the normalized keyword, exact matches count fully
+    /// and other matches count for a part
+    fn match_weight(self, token: &str, keyword: &str) -> f64 {
+        if token == keyword {
+            return 1.0;
+        }
+        if self == Strictness::Exact || self == Strictness::Word {
+            return 0.0;
+        }
+
+        if token.starts_with(keyword) {
+            0.5
+        } else if token.contains(keyword) {
+            0.25
+        } else if self == Strictness::Fuzzy && is_typo(token, keyword) {
+            0.4
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Check if the words are within the typo tolerance of each other, longer words allow more
+/// edits
+fn is_typo(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let allowed = match a.len().min(b.len()) {
+        0..=3 => return false,
+        4..=7 => 1,
+        _ => 2,
+    };
+    if (a.len() as isize - b.len() as isize).abs() > allowed as isize {
+        return false;
+    }
+    edit_distance(&a, &b) <= allowed
+}
+
+/// Damerau-Levenshtein distance (optimal string alignment), so a swap counts as one edit
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// Find the lines of the snippet body that contain one of the keywords
+pub fn search_body(
+    lines: &[(usize, String)],
+    keywords: &[String],
+    strictness: Strictness,
+) -> Vec<snippet::BodyMatch> {
+    let keywords: Vec<(String, &String)> = keywords
+        .iter()
+        .map(|k| (strictness.normalize(k), k))
+        .filter(|(normalized, _)| !normalized.is_empty())
+        .collect();
+    if keywords.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    for (line, text) in lines {
+        let lower = strictness.normalize(text);
+        let found: Vec<String> = keywords
+            .iter()
+            .filter(|(k, _)| lower.contains(k.as_str()))
+            .map(|(_, keyword)| keyword.to_string())
+            .collect();
+        if !found.is_empty() {
+            matches.push(snippet::BodyMatch {
+                line: *line,
+                text: text.trim().to_string(),
+                keywords: found,
+            });
+        }
+    }
+    matches
+}
+
+/// A snippet with its searchable text split in fields
+pub struct Document {
+    pub snippet: snippet::Snippet,
+    title: Vec<String>,
+    tags: Vec<String>,
+    body: Vec<String>,
+    /// Normalized text of the fields, for matching phrases
+    title_text: String,
+    body_text: String,
+    strictness: Strictness,
+}
+
+impl Document {
+    /// Create a document of the snippet, the body is only searched when given
+    pub fn new(
+        snippet: snippet::Snippet,
+        body: Option<&[(usize, String)]>,
+        strictness: Strictness,
+    ) -> Document {
+        let title = snippet
+            .title
+            .as_ref()
+            .map_or(Vec::new(), |t| tokenize(t, strictness));
+        let tags = snippet
+            .tags
+            .iter()
+            .map(|t| t.as_str())
+            .chain(snippet.categories())
+            .map(|t| strictness.normalize(t))
+            .collect();
+        let body = body.map_or(Vec::new(), |lines| {
+            lines
+                .iter()
+                .flat_map(|(_, line)| tokenize(line, strictness))
+                .collect()
+        });
+        Document {
+            title_text: title.join(" "),
+            body_text: body.join(" "),
+            snippet,
+            title,
+            tags,
+            body,
+            strictness,
+        }
+    }
+
+    /// Term frequency of the keyword for each of the fields, in the order title, tags, body
+    fn frequencies(&self, field: Field, keyword: &str) -> [f64; 3] {
+        let any = field == Field::Any;
+        [
+            if any || field == Field::Title {
+                self.frequency(&self.title, keyword)
+            } else {
+                0.0
+            },
+            if any || field == Field::Tag {
+                self.frequency(&self.tags, keyword)
+            } else {
+                0.0
+            },
+            if any {
+                self.frequency(&self.body, keyword)
+            } else {
+                0.0
+            },
+        ]
+    }
+
+    fn frequency(&self, tokens: &[String], keyword: &str) -> f64 {
+        tokens
+            .iter()
+            .map(|t| self.strictness.match_weight(t, keyword))
+            .sum()
+    }
+
+    /// Check if the term or phrase is found in the field
+    fn matches(&self, field: Field, term: &str, phrase: bool) -> bool {
+        let strictness = self.strictness;
+        let term = &strictness.normalize(term);
+        match field {
+            // The language in the front matter or of any of the code blocks
+            Field::Lang => self
+                .snippet
+                .language
+                .iter()
+                .chain(&self.snippet.code_languages)
+                .any(|l| &strictness.normalize(l) == term),
+            // Relative to the location, the folders above it would match every snippet
+            Field::Path => strictness
+                .normalize(&self.snippet.short_name())
+                .contains(term.as_str()),
+            Field::Tag if phrase => self.tags.iter().any(|t| t == term),
+            _ if phrase => {
+                let phrase = tokenize(term, strictness).join(" ");
+                let any = field == Field::Any;
+                (any || field == Field::Title) && self.title_text.contains(&phrase)
+                    || any && self.tags.iter().any(|t| t == term)
+                    || any && self.body_text.contains(&phrase)
+            }
+            _ => self.frequencies(field, term).iter().any(|&tf| tf > 0.0),
+        }
+    }
+
+    fn lengths(&self) -> [f64; 3] {
+        [
+            self.title.len() as f64,
+            self.tags.len() as f64,
+            self.body.len() as f64,
+        ]
+    }
+}
+
+/// Split text into normalized words, without the punctuation around them
+fn tokenize(text: &str, strictness: Strictness) -> Vec<String> {
+    text.split_whitespace()
+        .map(|w| strictness.normalize(w.trim_matches(|c: char| !c.is_alphanumeric())))
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/// Return the snippets that match the query, scored with BM25 over the title, tags and body
+/// and best first. Equal scores are ordered by the most recently modified, then by path
+pub fn rank(
+    documents: Vec<Document>,
+    query: &Query,
+    config: &SearchConfig,
+    strictness: Strictness,
+) -> Vec<snippet::Snippet> {
+    let keywords: Vec<(Field, String)> = query
+        .positive_terms()
+        .into_iter()
+        .map(|(field, term)| (field, strictness.normalize(&term)))
+        .collect();
+    let boosts = [config.title_boost, config.tags_boost, config.body_boost];
+
+    // Average length of the fields over all documents
+    let count = documents.len().max(1) as f64;
+    let mut average = [0.0; 3];
+    for document in &documents {
+        for (avg, len) in average.iter_mut().zip(document.lengths().iter()) {
+            *avg += len / count;
+        }
+    }
+
+    // Frequencies for every document and keyword
+    let frequencies: Vec<Vec<[f64; 3]>> = documents
+        .iter()
+        .map(|d| {
+            keywords
+                .iter()
+                .map(|(field, k)| d.frequencies(*field, k))
+                .collect()
+        })
+        .collect();
+
+    // Inverse document frequency of the keywords
+    let idf: Vec<f64> = (0..keywords.len())
+        .map(|i| {
+            let n = frequencies
+                .iter()
+                .filter(|f| f[i].iter().any(|&tf| tf > 0.0))
+                .count() as f64;
+            (1.0 + (count - n + 0.5) / (n + 0.5)).ln()
+        })
+        .collect();
+
+    let mut scored: Vec<snippet::Snippet> = documents
+        .into_iter()
+        .zip(frequencies)
+        .filter_map(|(document, frequencies)| {
+            if !query.matches(&|field, term, phrase| document.matches(field, term, phrase)) {
+                return None;
+            }
+
+            let lengths = document.lengths();
+            let mut score = 0.0;
+            for (i, fields) in frequencies.iter().enumerate() {
+                for field in 0..3 {
+                    let tf = fields[field];
+                    if tf == 0.0 {
+                        continue;
+                    }
+                    let norm = if average[field] > 0.0 {
+                        1.0 - B + B * lengths[field] / average[field]
+                    } else {
+                        1.0
+                    };
+                    score += idf[i] * boosts[field] * tf * (K1 + 1.0) / (tf + K1 * norm);
+                }
+            }
+
+            let mut snippet = document.snippet;
+            snippet.score = score;
+            Some(snippet)
+        })
+        .collect();
+
+    scored.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| b.modified.cmp(&a.modified))
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.anchor.cmp(&b.anchor))
+    });
+    scored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: &str, b: &str) -> usize {
+        let a: Vec<char> = a.chars().collect();
+        let b: Vec<char> = b.chars().collect();
+        edit_distance(&a, &b)
+    }
+
+    fn document(name: &str, title: &str, tags: &[&str], body: &str) -> Document {
+        let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+        let mut snippet = snippet::Snippet::new(name.to_string(), &tags);
+        snippet.title = Some(title.to_string());
+        let body = vec![(1, body.to_string())];
+        Document::new(snippet, Some(&body), Strictness::default())
+    }
+
+    fn ranked(documents: Vec<Document>, keyword: &str, config: &SearchConfig) -> Vec<String> {
+        let query = Query::parse(&[keyword.to_string()], false).unwrap();
+        rank(documents, &query, config, Strictness::default())
+            .iter()
+            .map(|s| s.full_name())
+            .collect()
+    }
+
+    #[test]
+    fn rank_boosts_tags_over_title_over_body() {
+        let documents = || {
+            vec![
+                document("body.md", "notes", &["misc"], "docker"),
+                document("title.md", "docker", &["misc"], "text"),
+                document("none.md", "notes", &["misc"], "text"),
+                document("tags.md", "notes", &["docker"], "text"),
+            ]
+        };
+        let config = SearchConfig::default();
+        assert_eq!(
+            ranked(documents(), "docker", &config),
+            vec!["tags.md", "title.md", "body.md"]
+        );
+
+        let config = SearchConfig {
+            body_boost: 10.0,
+            ..SearchConfig::default()
+        };
+        assert_eq!(
+            ranked(documents(), "docker", &config),
+            vec!["body.md", "tags.md", "title.md"]
+        );
+    }
+
+    #[test]
+    fn rank_scores_more_occurrences_higher() {
+        let documents = vec![
+            document("once.md", "notes", &["misc"], "docker compose"),
+            document("twice.md", "notes", &["misc"], "docker docker"),
+        ];
+        assert_eq!(
+            ranked(documents, "docker", &SearchConfig::default()),
+            vec!["twice.md", "once.md"]
+        );
+    }
+
+    #[test]
+    fn lang_matches_the_front_matter_and_code_block_languages() {
+        let mut documents = vec![
+            document("front.md", "notes", &[], ""),
+            document("fence.md", "notes", &[], ""),
+            document("other.md", "notes", &[], ""),
+        ];
+        documents[0].snippet.language = Some("bash".to_string());
+        documents[1].snippet.code_languages = vec!["yaml".to_string(), "Bash".to_string()];
+        documents[2].snippet.code_languages = vec!["python".to_string()];
+        let mut found = ranked(documents, "lang:bash", &SearchConfig::default());
+        found.sort();
+        assert_eq!(found, vec!["fence.md", "front.md"]);
+    }
+
+    #[test]
+    fn rank_orders_equal_scores_by_recency_path_and_anchor() {
+        let at = |secs| Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs));
+        let mut documents = Vec::new();
+        for (name, anchor, modified) in &[
+            ("c.md", None, 10),
+            ("a.md", Some("y"), 10),
+            ("b.md", None, 20),
+            ("a.md", Some("x"), 10),
+            ("a.md", None, 10),
+        ] {
+            let mut document = document(name, "docker", &[], "");
+            document.snippet.anchor = anchor.map(|a| a.to_string());
+            document.snippet.modified = at(*modified);
+            documents.push(document);
+        }
+        assert_eq!(
+            ranked(documents, "docker", &SearchConfig::default()),
+            vec!["b.md", "a.md", "a.md#x", "a.md#y", "c.md"]
+        );
+    }
+
+    #[test]
+    fn edit_distance_counts_edits() {
+        assert_eq!(distance("", ""), 0);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("docker", "docker"), 0);
+        assert_eq!(distance("docker", "dokcer"), 1);
+        assert_eq!(distance("docker", "docer"), 1);
+        assert_eq!(distance("docker", "dockers"), 1);
+        assert_eq!(distance("docker", "dacker"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn edit_distance_counts_characters_not_bytes() {
+        assert_eq!(distance("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn short_words_are_never_typos() {
+        assert!(!is_typo("git", "gti"));
+        assert!(!is_typo("vim", "vim"));
+    }
+
+    #[test]
+    fn longer_words_allow_more_edits() {
+        assert!(is_typo("docker", "dokcer"));
+        assert!(!is_typo("docker", "dkcoer"));
+        assert!(is_typo("kubernetes", "kubernets"));
+        assert!(is_typo("kubernetes", "kuberntes"));
+        assert!(is_typo("kubernetes", "kubrentes"));
+        assert!(!is_typo("kubernetes", "kbrntes"));
+    }
+
+    #[test]
+    fn normalize_ignores_case_and_accents_except_exact() {
+        assert_eq!(Strictness::Word.normalize("Café"), "cafe");
+        assert_eq!(Strictness::Exact.normalize("Café"), "Café");
+    }
+
+    #[test]
+    fn match_weight_depends_on_strictness() {
+        assert_eq!(Strictness::Exact.match_weight("docker", "dock"), 0.0);
+        assert_eq!(Strictness::Prefix.match_weight("docker", "docker"), 1.0);
+        assert_eq!(Strictness::Prefix.match_weight("docker", "dock"), 0.5);
+        assert_eq!(Strictness::Prefix.match_weight("docker", "ocke"), 0.25);
+        assert_eq!(Strictness::Prefix.match_weight("docker", "dokcer"), 0.0);
+        assert_eq!(Strictness::Fuzzy.match_weight("docker", "dokcer"), 0.4);
+    }
+}
@@ -1,6 +1,9 @@
 use crate::error;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read};
+use std::path;
+use std::time::SystemTime;
 
 /**
  * The snippet struct that has uses multiple tags, to order the snippets
@@ -8,19 +11,217 @@ use std::io::{BufRead, BufReader};
 #[derive(Debug)]
 pub struct Snippet {
     pub name: String,
+    /// Anchor of the heading when the snippet is a section of a split file
+    pub anchor: Option<String>,
     pub tags: Vec<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    /// Languages of the fenced code blocks in the snippet
+    pub code_languages: Vec<String>,
+    pub aliases: Vec<String>,
+    pub created: Option<String>,
+    pub updated: Option<String>,
+    pub author: Option<String>,
+    /// Folder of the snippet relative to its location, `None` when in the root
+    pub folder: Option<String>,
+    /// Lines of the body that matched the keywords, when searching the body
+    pub body_matches: Vec<BodyMatch>,
+    /// Relevance for the searched keywords, higher is better
+    pub score: f64,
+    /// Last modification time of the snippet file
+    pub modified: Option<SystemTime>,
 }
 
 impl Snippet {
     pub fn new(name: String, tags: &Vec<String>) -> Snippet {
         Snippet {
             name,
+            anchor: None,
             tags: tags.to_owned(),
+            title: None,
+            description: None,
+            language: None,
+            code_languages: Vec::new(),
+            aliases: Vec::new(),
+            created: None,
+            updated: None,
+            author: None,
+            folder: None,
+            body_matches: Vec::new(),
+            score: 0.0,
+            modified: None,
         }
     }
+
+    /// Create a snippet from the metadata read from its file
+    pub fn from_metadata(name: String, metadata: Metadata) -> Snippet {
+        Snippet {
+            name,
+            anchor: None,
+            tags: metadata.tags,
+            title: metadata.title,
+            description: metadata.description,
+            language: metadata.language,
+            code_languages: metadata.code_languages,
+            aliases: metadata.aliases,
+            created: metadata.created,
+            updated: metadata.updated,
+            author: metadata.author,
+            folder: None,
+            body_matches: Vec::new(),
+            score: 0.0,
+            modified: None,
+        }
+    }
+
+    /// The title to show, the file name without its extension when the snippet has no title
+    pub fn display_title(&self) -> String {
+        match self.title {
+            Some(ref title) => title.clone(),
+            None => path::Path::new(&self.name)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+
+    /// The path of the snippet, with the anchor for a section like `/snippets/cheats.md#logs`
+    pub fn full_name(&self) -> String {
+        match self.anchor {
+            Some(ref anchor) => format!("{}#{}", self.name, anchor),
+            None => self.name.clone(),
+        }
+    }
+
+    /// The name of the snippet in its location, like `docker/prune.md` or `cheats.md#logs`
+    pub fn short_name(&self) -> String {
+        let file_name = path::Path::new(&self.name)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(&self.name);
+        let mut name = match self.folder {
+            Some(ref folder) => format!("{}/{}", folder, file_name),
+            None => file_name.to_string(),
+        };
+        if let Some(ref anchor) = self.anchor {
+            name.push('#');
+            name.push_str(anchor);
+        }
+        name
+    }
+
+    /// The folders the snippet is in, these can be used as implicit tags
+    pub fn categories(&self) -> Vec<&str> {
+        match self.folder {
+            Some(ref folder) => folder.split('/').filter(|s| !s.is_empty()).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// A line in the body of the snippet that matched a keyword
+#[derive(Debug, Clone)]
+pub struct BodyMatch {
+    /// Line number in the snippet file, starting at 1
+    pub line: usize,
+    pub text: String,
+    /// The keywords found on the line
+    pub keywords: Vec<String>,
 }
 
-pub fn read_tags(path: &str) -> Result<Vec<String>, error::Error> {
+/// Metadata of a snippet, either read from a front matter block or from the legacy tag line
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    /// Languages of the fenced code blocks, these are found in the body and not read from the
+    /// front matter
+    pub code_languages: Vec<String>,
+    pub aliases: Vec<String>,
+    pub created: Option<String>,
+    pub updated: Option<String>,
+    pub author: Option<String>,
+}
+
+/// The kind of front matter block, determined by its delimiter
+enum FrontMatter {
+    Yaml,
+    Toml,
+}
+
+impl FrontMatter {
+    fn from_delimiter(line: &str) -> Option<FrontMatter> {
+        match line.trim_end() {
+            "---" => Some(FrontMatter::Yaml),
+            "+++" => Some(FrontMatter::Toml),
+            _ => None,
+        }
+    }
+
+    fn delimiter(&self) -> &'static str {
+        match self {
+            FrontMatter::Yaml => "---",
+            FrontMatter::Toml => "+++",
+        }
+    }
+
+    fn parse(&self, content: &str) -> Result<Metadata, error::Error> {
+        match self {
+            FrontMatter::Yaml => {
+                // An empty block is valid, but serde_yaml does not accept an empty document
+                if content.trim().is_empty() {
+                    return Ok(Metadata::default());
+                }
+                Ok(serde_yaml::from_str(content)?)
+            }
+            FrontMatter::Toml => {
+                let mut value: toml::Value = toml::from_str(content)?;
+
+                // Dates are native values in TOML, store them as strings like in YAML
+                if let toml::Value::Table(ref mut table) = value {
+                    for (_, v) in table.iter_mut() {
+                        let date = match v {
+                            toml::Value::Datetime(d) => Some(d.to_string()),
+                            _ => None,
+                        };
+                        if let Some(date) = date {
+                            *v = toml::Value::String(date);
+                        }
+                    }
+                }
+                Ok(value.try_into()?)
+            }
+        }
+    }
+}
+
+/// Read the metadata of the snippet. A title and description that are not in the front matter
+/// are taken from the first heading and the first line of text
+pub fn read_metadata(path: &str) -> Result<Metadata, error::Error> {
+    let mut metadata = read_header(path)?;
+
+    let mut content = String::new();
+    File::open(path)?.read_to_string(&mut content)?;
+    let lines: Vec<&str> = content.lines().collect();
+    let body = &lines[header_length(&lines)..];
+    if metadata.title.is_none() {
+        metadata.title = find_headings(&lines).into_iter().next().map(|h| h.title);
+    }
+    if metadata.description.is_none() {
+        metadata.description = first_text_line(body);
+    }
+    metadata.code_languages = code_languages(body);
+    Ok(metadata)
+}
+
+/// Read the front matter block delimited by `---` (YAML) or `+++` (TOML) when present,
+/// otherwise the first line is read as a list of tags
+fn read_header(path: &str) -> Result<Metadata, error::Error> {
     // Open the file
     let f = File::open(path)?;
     let mut file = BufReader::new(f);
@@ -29,12 +230,807 @@ pub fn read_tags(path: &str) -> Result<Vec<String>, error::Error> {
     let mut buffer = String::new();
     file.read_line(&mut buffer)?;
 
-    // Read the tags, remove empty ones
-    let mut t: Vec<&str> = buffer.as_str().split(',').map(|s| s.trim()).collect();
+    let front_matter = match FrontMatter::from_delimiter(&buffer) {
+        Some(front_matter) => front_matter,
+        None => {
+            return Ok(Metadata {
+                tags: parse_tag_line(&buffer),
+                ..Metadata::default()
+            })
+        }
+    };
+
+    // Collect the block up to the closing delimiter
+    let mut content = String::new();
+    for line in file.lines() {
+        let line = line?;
+        if line.trim_end() == front_matter.delimiter() {
+            // A single broken file should not stop the search, use it without tags instead
+            return Ok(front_matter.parse(&content).unwrap_or_else(|e| {
+                eprintln!("Invalid front matter in {}: {}", path, e);
+                Metadata::default()
+            }));
+        }
+        content.push_str(&line);
+        content.push('\n');
+    }
+
+    // No closing delimiter, so this was not a front matter block after all
+    Ok(Metadata::default())
+}
+
+/// A heading in a snippet file, the section under it can be used as a separate snippet
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Heading {
+    /// Line number in the file, starting at 1
+    pub line: usize,
+    /// 1 for `#`, 2 for `##` and so on
+    pub level: usize,
+    pub title: String,
+    /// Unique in the file, made like GitHub does: `Prune images` becomes `prune-images`
+    pub anchor: String,
+    /// Tags from a `tags:` line right below the heading
+    pub tags: Vec<String>,
+    /// The first line of text in the section
+    pub description: Option<String>,
+    /// Languages of the fenced code blocks in the section
+    #[serde(default)]
+    pub code_languages: Vec<String>,
+}
+
+/// Read the headings of the snippet file, outside of code blocks
+pub fn read_headings(path: &str) -> Result<Vec<Heading>, error::Error> {
+    let mut content = String::new();
+    File::open(path)?.read_to_string(&mut content)?;
+    let lines: Vec<&str> = content.lines().collect();
+    Ok(find_headings(&lines))
+}
+
+/// Read the lines of the snippet body, without the front matter block or tag line, or the
+/// body of the section with the anchor. The lines are numbered from the start of the file
+pub fn read_body(path: &str, anchor: Option<&str>) -> Result<Vec<(usize, String)>, error::Error> {
+    let mut content = String::new();
+    File::open(path)?.read_to_string(&mut content)?;
+
+    let lines: Vec<&str> = content.lines().collect();
+    let (skip, end) = match anchor {
+        Some(anchor) => {
+            let (_, body, end) = find_section(path, &lines, anchor)?;
+            (body, end)
+        }
+        None => (header_length(&lines), lines.len()),
+    };
+    Ok(lines
+        .iter()
+        .enumerate()
+        .take(end)
+        .skip(skip)
+        .map(|(i, line)| (i + 1, line.to_string()))
+        .collect())
+}
+
+/// Read the whole snippet file, or only the section with its heading for an anchor
+pub fn read_content(path: &str, anchor: Option<&str>) -> Result<String, error::Error> {
+    let mut content = String::new();
+    File::open(path)?.read_to_string(&mut content)?;
+
+    let anchor = match anchor {
+        Some(anchor) => anchor,
+        None => return Ok(content),
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let (start, _, end) = find_section(path, &lines, anchor)?;
+    let mut section = lines[start..end].join("\n");
+    section.push('\n');
+    Ok(section)
+}
+
+/// The line of the heading of the section with the anchor
+pub fn locate(path: &str, anchor: &str) -> Result<usize, error::Error> {
+    read_headings(path)?
+        .into_iter()
+        .find(|h| h.anchor == anchor)
+        .map(|h| h.line)
+        .ok_or_else(|| no_section(path, anchor))
+}
+
+fn no_section(path: &str, anchor: &str) -> error::Error {
+    error::Error::InternalError(format!("{} has no section `{}`", path, anchor))
+}
+
+/// The section under the heading with the anchor, as the index of the line of the heading, of
+/// the first line of the body and of the line after the section. The section ends at the next
+/// heading of the same or a higher level
+fn find_section(
+    path: &str,
+    lines: &[&str],
+    anchor: &str,
+) -> Result<(usize, usize, usize), error::Error> {
+    let headings = find_headings(lines);
+    let index = headings
+        .iter()
+        .position(|h| h.anchor == anchor)
+        .ok_or_else(|| no_section(path, anchor))?;
+    let heading = &headings[index];
+
+    let start = heading.line - 1;
+    let has_tags = lines
+        .get(start + 1)
+        .and_then(|l| parse_section_tags(l))
+        .is_some();
+    let body = if has_tags { start + 2 } else { start + 1 };
+    let end = headings[index + 1..]
+        .iter()
+        .find(|h| h.level <= heading.level)
+        .map_or(lines.len(), |h| h.line - 1);
+    Ok((start, body, end))
+}
+
+/// Find the markdown headings after the front matter block or tag line, skipping code blocks
+/// where `#` starts a comment
+fn find_headings(lines: &[&str]) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut anchors: HashSet<String> = HashSet::new();
+    let mut fence: Option<(char, usize)> = None;
+
+    for (i, line) in lines.iter().enumerate().skip(header_length(lines)) {
+        let trimmed = line.trim_start();
+        if is_fence(trimmed, &mut fence) || fence.is_some() {
+            continue;
+        }
+
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        let rest = &trimmed[level..];
+        // Not a heading, but e.g. `#hashtag`
+        if level == 0 || level > 6 || !(rest.is_empty() || rest.starts_with(' ')) {
+            continue;
+        }
+        let title = rest.trim().trim_end_matches('#').trim();
+        if title.is_empty() {
+            continue;
+        }
+
+        // Number the anchors of headings with the same title, skipping numbers that are
+        // already the anchor of another heading
+        let base = slug(title);
+        let mut anchor = base.clone();
+        let mut number = 0;
+        while anchors.contains(&anchor) {
+            number += 1;
+            anchor = format!("{}-{}", base, number);
+        }
+        anchors.insert(anchor.clone());
+
+        headings.push(Heading {
+            line: i + 1,
+            level,
+            title: title.to_string(),
+            anchor,
+            tags: lines
+                .get(i + 1)
+                .and_then(|l| parse_section_tags(l))
+                .unwrap_or_default(),
+            description: None,
+            code_languages: Vec::new(),
+        });
+    }
+
+    // Describe the sections with their first line of text and the languages of their code
+    for i in 0..headings.len() {
+        let end = headings[i + 1..]
+            .iter()
+            .find(|h| h.level <= headings[i].level)
+            .map_or(lines.len(), |h| h.line - 1);
+        let section = &lines[headings[i].line..end];
+        headings[i].description = first_text_line(section);
+        headings[i].code_languages = code_languages(section);
+    }
+    headings
+}
+
+/// Check if the line opens or closes a code block fenced by ``` or ~~~, keeping track of the
+/// fence character and length of the open block. Like in `find_code_blocks` a block is only
+/// closed by a fence of the same character, at least as long and without an info string
+fn is_fence(trimmed: &str, fence: &mut Option<(char, usize)>) -> bool {
+    if !trimmed.starts_with("```") && !trimmed.starts_with("~~~") {
+        return false;
+    }
+    let c = trimmed.chars().next().unwrap_or('`');
+    let length = trimmed.chars().take_while(|t| *t == c).count();
+    match *fence {
+        None => *fence = Some((c, length)),
+        Some((f, open)) if f == c && length >= open && trimmed.trim_end().len() == length => {
+            *fence = None
+        }
+        // Part of the code in the open block
+        Some(_) => return false,
+    }
+    true
+}
+
+/// The first line of text, skipping headings, code blocks and `tags:` lines, without the
+/// markers of lists and quotes
+fn first_text_line(lines: &[&str]) -> Option<String> {
+    let mut fence: Option<(char, usize)> = None;
+    for line in lines {
+        let trimmed = line.trim();
+        if is_fence(trimmed, &mut fence) || fence.is_some() {
+            continue;
+        }
+        // Headings and the underlines of `===` headings
+        if trimmed.starts_with('#')
+            || trimmed.chars().all(|c| c == '=')
+            || parse_section_tags(trimmed).is_some()
+        {
+            continue;
+        }
+        let text = trimmed.trim_start_matches(['*', '-', '+', '>']).trim();
+        if !text.is_empty() {
+            return Some(text.to_string());
+        }
+    }
+    None
+}
+
+/// The anchor for a heading: lower case, without punctuation and with dashes for spaces
+fn slug(title: &str) -> String {
+    title
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Read the tags of a section from a line like `tags: docker, compose` or
+/// `tags: [docker, compose]`
+fn parse_section_tags(line: &str) -> Option<Vec<String>> {
+    let line = line.trim();
+    if !line
+        .get(..5)
+        .is_some_and(|t| t.eq_ignore_ascii_case("tags:"))
+    {
+        return None;
+    }
+    let list = line[5..]
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    Some(parse_tag_line(list))
+}
+
+/// A fenced code block in the body of a snippet
+#[derive(Debug, Clone)]
+pub struct CodeBlock {
+    /// The language after the opening fence, if any
+    pub language: Option<String>,
+    /// Line number of the opening fence in the snippet file, starting at 1
+    pub line: usize,
+    /// The code without the fences
+    pub code: String,
+}
+
+/// Find the code blocks fenced by ``` or ~~~ in the body of the snippet or of the section with
+/// the anchor, a block without a closing fence runs to the end of the file
+pub fn read_code_blocks(path: &str, anchor: Option<&str>) -> Result<Vec<CodeBlock>, error::Error> {
+    Ok(find_code_blocks(read_body(path, anchor)?))
+}
+
+/// Find the fenced code blocks in the numbered lines
+fn find_code_blocks(lines: Vec<(usize, String)>) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    // The fence character and length of the open block
+    let mut open: Option<(char, usize)> = None;
+
+    for (line, text) in lines {
+        let trimmed = text.trim_start();
+        let fence = trimmed
+            .chars()
+            .next()
+            .filter(|c| *c == '`' || *c == '~')
+            .map(|c| (c, trimmed.chars().take_while(|t| *t == c).count()))
+            .filter(|(_, length)| *length >= 3);
+
+        match (open, fence) {
+            (None, Some((c, length))) => {
+                let language = trimmed[length..]
+                    .split_whitespace()
+                    .next()
+                    .map(|l| l.to_string());
+                blocks.push(CodeBlock {
+                    language,
+                    line,
+                    code: String::new(),
+                });
+                open = Some((c, length));
+            }
+            // Closed by the same character, at least as long and without an info string
+            (Some((c, length)), Some((f, fence_length)))
+                if c == f && fence_length >= length && trimmed.trim_end().len() == fence_length =>
+            {
+                open = None;
+            }
+            (Some(_), _) => {
+                if let Some(block) = blocks.last_mut() {
+                    block.code.push_str(&text);
+                    block.code.push('\n');
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    blocks
+}
+
+/// The distinct languages of the fenced code blocks in the lines, in the order they are found
+fn code_languages(lines: &[&str]) -> Vec<String> {
+    let numbered = lines
+        .iter()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.to_string()))
+        .collect();
+    let mut languages: Vec<String> = Vec::new();
+    for language in find_code_blocks(numbered)
+        .into_iter()
+        .filter_map(|b| b.language)
+    {
+        if !languages.contains(&language) {
+            languages.push(language);
+        }
+    }
+    languages
+}
+
+/// A value to fill in, written as `{{name}}`, `{{name:default}}` or `<name>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    pub name: String,
+    pub default: Option<String>,
+}
+
+/// A placeholder in the text, with its byte range
+struct Found {
+    start: usize,
+    end: usize,
+    placeholder: Placeholder,
+}
+
+/// Placeholder names are words that can contain `-` and `_`
+fn is_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Find the placeholders in the text, in order
+fn find_all(text: &str) -> Vec<Found> {
+    let mut found = Vec::new();
+    let mut position = 0;
+
+    while position < text.len() {
+        let rest = &text[position..];
+        if rest.starts_with("{{") {
+            if let Some(close) = rest.find("}}") {
+                let inner = &rest[2..close];
+                let (name, default) = match inner.find(':') {
+                    Some(colon) => (&inner[..colon], Some(inner[colon + 1..].trim().to_string())),
+                    None => (inner, None),
+                };
+                let name = name.trim();
+                if is_name(name) {
+                    found.push(Found {
+                        start: position,
+                        end: position + close + 2,
+                        placeholder: Placeholder {
+                            name: name.to_string(),
+                            default,
+                        },
+                    });
+                    position += close + 2;
+                    continue;
+                }
+            }
+        } else if rest.starts_with('<') {
+            // Not directly after a word, so `Vec<T>` is not a placeholder
+            let after_word = text[..position]
+                .chars()
+                .last()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_');
+            if let Some(close) = rest.find('>') {
+                let name = &rest[1..close];
+                if !after_word && is_name(name) {
+                    found.push(Found {
+                        start: position,
+                        end: position + close + 1,
+                        placeholder: Placeholder {
+                            name: name.to_string(),
+                            default: None,
+                        },
+                    });
+                    position += close + 1;
+                    continue;
+                }
+            }
+        }
+        position += rest.chars().next().map_or(1, |c| c.len_utf8());
+    }
+    found
+}
+
+/// The placeholders in the text, each name once with the first default given for it
+pub fn find_placeholders(text: &str) -> Vec<Placeholder> {
+    let mut placeholders: Vec<Placeholder> = Vec::new();
+    for found in find_all(text) {
+        match placeholders
+            .iter_mut()
+            .find(|p| p.name == found.placeholder.name)
+        {
+            Some(existing) => {
+                if existing.default.is_none() {
+                    existing.default = found.placeholder.default;
+                }
+            }
+            None => placeholders.push(found.placeholder),
+        }
+    }
+    placeholders
+}
+
+/// Replace the placeholders by their values, placeholders without a value are kept
+pub fn fill_placeholders(text: &str, values: &HashMap<String, String>) -> String {
+    let mut filled = String::new();
+    let mut position = 0;
+    for found in find_all(text) {
+        if let Some(value) = values.get(&found.placeholder.name) {
+            filled.push_str(&text[position..found.start]);
+            filled.push_str(value);
+            position = found.end;
+        }
+    }
+    filled.push_str(&text[position..]);
+    filled
+}
+
+/// The number of lines used by the front matter block or the tag line
+fn header_length(lines: &[&str]) -> usize {
+    match lines.first().map(|l| FrontMatter::from_delimiter(l)) {
+        Some(Some(front_matter)) => lines
+            .iter()
+            .skip(1)
+            .position(|l| l.trim_end() == front_matter.delimiter())
+            .map_or(0, |i| i + 2),
+        Some(None) => 1,
+        None => 0,
+    }
+}
+
+/// Read the tags from a comma separated line, remove empty ones
+fn parse_tag_line(line: &str) -> Vec<String> {
+    let mut t: Vec<&str> = line.split(',').map(|s| s.trim()).collect();
     t.retain(|s| !s.is_empty());
 
-    let tags: Vec<String> = t.iter().map(|s| String::from(s.to_owned())).collect();
+    t.iter().map(|s| String::from(s.to_owned())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(text: &str) -> Vec<(usize, String)> {
+        text.lines()
+            .enumerate()
+            .map(|(i, l)| (i + 1, l.to_string()))
+            .collect()
+    }
+
+    fn placeholder(name: &str, default: Option<&str>) -> Placeholder {
+        Placeholder {
+            name: name.to_string(),
+            default: default.map(|d| d.to_string()),
+        }
+    }
+
+    /// Write the content to a file in the temp dir and read its header
+    fn header_of(name: &str, content: &str) -> Metadata {
+        let path =
+            std::env::temp_dir().join(format!("rusty-x-test-{}-{}.md", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        let metadata = read_header(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        metadata
+    }
+
+    #[test]
+    fn read_header_from_yaml_front_matter() {
+        let metadata = header_of(
+            "yaml",
+            "---\ntitle: Compose\ntags: [docker, compose]\ncreated: 2020-01-02\n---\n# Body\n",
+        );
+        assert_eq!(metadata.title.as_deref(), Some("Compose"));
+        assert_eq!(metadata.tags, vec!["docker", "compose"]);
+        assert_eq!(metadata.created.as_deref(), Some("2020-01-02"));
+    }
+
+    #[test]
+    fn read_header_from_toml_front_matter_with_dates() {
+        let metadata = header_of(
+            "toml",
+            "+++\ntitle = \"Compose\"\ntags = [\"docker\"]\ncreated = 2020-01-02\nupdated = 2020-01-02T03:04:05Z\n+++\n",
+        );
+        assert_eq!(metadata.title.as_deref(), Some("Compose"));
+        assert_eq!(metadata.tags, vec!["docker"]);
+        assert_eq!(metadata.created.as_deref(), Some("2020-01-02"));
+        assert_eq!(metadata.updated.as_deref(), Some("2020-01-02T03:04:05Z"));
+    }
+
+    #[test]
+    fn read_header_from_the_legacy_tag_line() {
+        let metadata = header_of("legacy", "vim, editor,, \n# Vim\n");
+        assert_eq!(metadata.tags, vec!["vim", "editor"]);
+        assert!(metadata.title.is_none());
+    }
+
+    #[test]
+    fn read_header_without_usable_front_matter() {
+        for (name, content) in &[
+            ("empty", "---\n---\nvim\n"),
+            ("unclosed", "---\ntags: [vim]\n"),
+            ("invalid", "---\ntags: [vim\n---\n"),
+        ] {
+            let metadata = header_of(name, content);
+            assert!(metadata.tags.is_empty(), "{}", name);
+            assert!(metadata.title.is_none(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn front_matter_parse_rejects_invalid_blocks() {
+        assert!(FrontMatter::Yaml.parse("tags: [a").is_err());
+        assert!(FrontMatter::Toml.parse("tags = ").is_err());
+        assert!(FrontMatter::Yaml.parse("\n").unwrap().tags.is_empty());
+    }
+
+    #[test]
+    fn header_length_of_each_header() {
+        assert_eq!(header_length(&["---", "tags: [a]", "---", "body"]), 3);
+        assert_eq!(header_length(&["+++", "+++", "body"]), 2);
+        assert_eq!(header_length(&["vim, editor", "body"]), 1);
+        assert_eq!(header_length(&["---", "body"]), 0);
+        assert_eq!(header_length(&[]), 0);
+    }
+
+    #[test]
+    fn find_placeholders_in_both_styles() {
+        assert_eq!(
+            find_placeholders("docker run {{image}} -p {{port:8080}} <name>"),
+            vec![
+                placeholder("image", None),
+                placeholder("port", Some("8080")),
+                placeholder("name", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn find_placeholders_once_with_the_first_default() {
+        assert_eq!(
+            find_placeholders("{{a}} {{a:one}} {{a:two}} <a>"),
+            vec![placeholder("a", Some("one"))]
+        );
+    }
+
+    #[test]
+    fn find_placeholders_trims_names_and_defaults() {
+        assert_eq!(
+            find_placeholders("{{ url: http://localhost:80 }}"),
+            vec![placeholder("url", Some("http://localhost:80"))]
+        );
+        assert_eq!(
+            find_placeholders("{{x:}}"),
+            vec![placeholder("x", Some(""))]
+        );
+    }
+
+    #[test]
+    fn find_placeholders_skips_other_braces_and_brackets() {
+        // Template syntax of other tools and generics are not placeholders
+        assert!(find_placeholders("${{ secrets.TOKEN }} {{}} {{1x}} Vec<String> a<b>").is_empty());
+        assert!(find_placeholders("cat <<EOF\n<!-- x --> a < b > c").is_empty());
+        assert!(find_placeholders("{{unclosed <also").is_empty());
+    }
+
+    #[test]
+    fn find_placeholders_in_extra_braces() {
+        assert_eq!(
+            find_placeholders("{{{name}}}"),
+            vec![placeholder("name", None)]
+        );
+    }
+
+    #[test]
+    fn find_placeholders_after_multibyte_characters() {
+        assert_eq!(
+            find_placeholders("é <naïve> ü"),
+            vec![placeholder("naïve", None)]
+        );
+    }
+
+    #[test]
+    fn fill_placeholders_replaces_every_occurrence() {
+        let mut values = HashMap::new();
+        values.insert("image".to_string(), "alpine".to_string());
+        values.insert("port".to_string(), "80".to_string());
+        assert_eq!(
+            fill_placeholders("{{image}} {{port:8080}} <image> {{{image}}}", &values),
+            "alpine 80 alpine {alpine}"
+        );
+    }
+
+    #[test]
+    fn fill_placeholders_keeps_placeholders_without_value() {
+        let mut values = HashMap::new();
+        values.insert("a".to_string(), "{{b}}".to_string());
+        // Values are not filled in again
+        assert_eq!(
+            fill_placeholders("<a> {{b}} Vec<a>", &values),
+            "{{b}} {{b}} Vec<a>"
+        );
+    }
+
+    #[test]
+    fn find_code_blocks_with_language_and_line() {
+        let blocks = find_code_blocks(numbered(
+            "Text\n```bash title=x\necho a\n\necho b\n```\n~~~\nplain\n~~~\n",
+        ));
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].language, Some("bash".to_string()));
+        assert_eq!(blocks[0].line, 2);
+        assert_eq!(blocks[0].code, "echo a\n\necho b\n");
+        assert_eq!(blocks[1].language, None);
+        assert_eq!(blocks[1].line, 7);
+        assert_eq!(blocks[1].code, "plain\n");
+    }
+
+    #[test]
+    fn find_code_blocks_nested_in_longer_fences() {
+        let blocks = find_code_blocks(numbered("````markdown\n```bash\nls\n```\n````\n"));
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].language, Some("markdown".to_string()));
+        assert_eq!(blocks[0].code, "```bash\nls\n```\n");
+    }
+
+    #[test]
+    fn find_code_blocks_closes_only_on_a_matching_fence() {
+        // A tilde fence, a shorter fence and a fence with an info string do not close
+        let blocks = find_code_blocks(numbered("```\n~~~\n``\n``` x\n  ````\nafter\n"));
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].code, "~~~\n``\n``` x\n");
+    }
+
+    #[test]
+    fn find_code_blocks_runs_an_unclosed_block_to_the_end() {
+        let blocks = find_code_blocks(numbered("```sh\necho a\necho b"));
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].code, "echo a\necho b\n");
+    }
+
+    #[test]
+    fn find_code_blocks_skips_text_and_indented_code() {
+        assert!(find_code_blocks(numbered("Text with ``` inline\n    indented\n")).is_empty());
+    }
+
+    #[test]
+    fn slug_keeps_words_and_dashes() {
+        assert_eq!(slug("Prune images"), "prune-images");
+        assert_eq!(slug("What's new? (v2.0)"), "whats-new-v20");
+        assert_eq!(slug("snake_case and-dash"), "snake_case-and-dash");
+        assert_eq!(slug("Café Ünïcode"), "café-ünïcode");
+        assert_eq!(slug("!!!"), "");
+    }
+
+    #[test]
+    fn find_headings_numbers_duplicate_anchors() {
+        // The first line is the tag line
+        let lines = ["logs", "# Logs", "## Logs", "# Logs-1", "# Logs"];
+        let anchors: Vec<String> = find_headings(&lines)
+            .into_iter()
+            .map(|h| h.anchor)
+            .collect();
+        assert_eq!(anchors, vec!["logs", "logs-1", "logs-1-1", "logs-2"]);
+    }
+
+    #[test]
+    fn find_headings_skips_code_and_hashtags() {
+        let lines = [
+            "tags",
+            "# Title #",
+            "#hashtag",
+            "````markdown",
+            "```bash",
+            "# not a heading",
+            "```",
+            "# still code",
+            "````",
+            "## Section",
+            "tags: [a, b]",
+            "####### too deep",
+            "#",
+        ];
+        let headings = find_headings(&lines);
+        let titles: Vec<&str> = headings.iter().map(|h| h.title.as_str()).collect();
+        assert_eq!(titles, vec!["Title", "Section"]);
+        assert_eq!(headings[0].line, 2);
+        assert_eq!(headings[1].level, 2);
+        assert_eq!(headings[1].tags, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn find_headings_describes_sections() {
+        let lines = [
+            "tags",
+            "# One",
+            "",
+            "```",
+            "code",
+            "```",
+            "- first text",
+            "## Two",
+            "# Three",
+        ];
+        let headings = find_headings(&lines);
+        assert_eq!(headings[0].description, Some("first text".to_string()));
+        assert_eq!(headings[1].description, None);
+    }
+
+    #[test]
+    fn find_headings_collects_the_code_languages_of_sections() {
+        let lines = [
+            "tags", "# One", "```bash", "ls", "```", "~~~bash", "~~~", "## Sub", "```yaml", "```",
+            "# Two", "```", "```",
+        ];
+        let languages: Vec<Vec<String>> = find_headings(&lines)
+            .into_iter()
+            .map(|h| h.code_languages)
+            .collect();
+        assert_eq!(languages, vec![vec!["bash", "yaml"], vec!["yaml"], vec![]]);
+    }
 
-    // Return the tags found
-    Ok(tags)
+    #[test]
+    fn find_section_ends_at_the_same_or_a_higher_level() {
+        let lines = [
+            "---",
+            "tags: [x]",
+            "---",
+            "## One",
+            "tags: a",
+            "text",
+            "### Sub",
+            "more",
+            "## Two",
+            "two",
+            "# Top",
+        ];
+        // The heading, the body after the tags line and the end, as indexes of the lines
+        assert_eq!(find_section("f.md", &lines, "one").unwrap(), (3, 5, 8));
+        assert_eq!(find_section("f.md", &lines, "sub").unwrap(), (6, 7, 8));
+        assert_eq!(find_section("f.md", &lines, "two").unwrap(), (8, 9, 10));
+        assert_eq!(find_section("f.md", &lines, "top").unwrap(), (10, 11, 11));
+        assert!(find_section("f.md", &lines, "three").is_err());
+    }
+
+    #[test]
+    fn is_fence_closes_on_a_matching_fence_only() {
+        let mut fence = None;
+        assert!(is_fence("````md", &mut fence));
+        assert!(!is_fence("```bash", &mut fence));
+        assert!(!is_fence("~~~~", &mut fence));
+        assert!(!is_fence("```` x", &mut fence));
+        assert!(fence.is_some());
+        assert!(is_fence("`````", &mut fence));
+        assert!(fence.is_none());
+    }
 }
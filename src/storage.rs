@@ -0,0 +1,20 @@
+use crate::error;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path;
+
+/// Write the content to a file in a dir of the user, like the index or the history, creating
+/// the dir when needed. The content is written to a temporary file next to it first, so an
+/// interrupted write does not leave a partly written file behind
+pub fn save_file(path: &path::Path, content: &str) -> Result<(), error::Error> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    File::create(&tmp_path)?.write_all(content.as_bytes())?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
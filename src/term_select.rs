@@ -1,10 +1,143 @@
-use crossterm::{cursor, input, terminal, AlternateScreen, InputEvent, KeyEvent, RawScreen, Terminal, TerminalCursor, ClearType, Crossterm, Colored, Color, Attribute, Styler};
+use crate::error::Error;
+use crate::history::History;
+use crate::project::{KeyConfig, PickerConfig};
+use crate::theme::Theme;
+use ansi_term::{ANSIStrings, Colour, Style};
+use crossterm::{
+    cursor, input, terminal, AlternateScreen, Attribute, ClearType, Crossterm, InputEvent,
+    KeyEvent, MouseButton, MouseEvent, RawScreen,
+};
 use fuzzy_matcher::skim::fuzzy_indices;
-use std::{iter::Iterator, thread, time};
 use std::cmp::max;
+use std::collections::HashMap;
+use std::{iter::Iterator, thread, time};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme as SyntaxTheme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// What to do with the chosen results
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    View,
+    Edit,
+    Copy,
+    Delete,
+    Run,
+    /// Rename or move, only used from the command line as it needs a destination
+    Move,
+}
+
+/// The results chosen in the picker, with the action to perform on them
+#[derive(Debug)]
+pub struct Selection {
+    pub indices: Vec<usize>,
+    pub action: Action,
+}
+
+/// Options for the picker
+pub struct PickerOptions {
+    action_keys: Vec<(KeyEvent, Action)>,
+    toggle_preview_key: KeyEvent,
+    /// Show the preview when the picker opens
+    preview: bool,
+    preview_position: PreviewPosition,
+    /// Show the match score after the results
+    show_score: bool,
+    /// Show the picker inline with this height instead of on the whole screen
+    height: Option<Height>,
+    theme: Theme,
+}
+
+impl PickerOptions {
+    /// Create the picker options from the configured keys, picker settings and theme
+    pub fn new(
+        keys: &KeyConfig,
+        config: &PickerConfig,
+        theme: &Theme,
+    ) -> Result<PickerOptions, Error> {
+        let action_keys = vec![
+            (parse_key(&keys.view)?, Action::View),
+            (parse_key(&keys.edit)?, Action::Edit),
+            (parse_key(&keys.copy)?, Action::Copy),
+            (parse_key(&keys.delete)?, Action::Delete),
+            (parse_key(&keys.run)?, Action::Run),
+        ];
+        Ok(PickerOptions {
+            action_keys,
+            toggle_preview_key: parse_key(&keys.toggle_preview)?,
+            preview: config.preview,
+            preview_position: config.preview_position,
+            show_score: config.show_score,
+            height: match config.height {
+                Some(ref height) => Some(Height::parse(height)?),
+                None => None,
+            },
+            theme: theme.clone(),
+        })
+    }
+}
+
+impl Default for PickerOptions {
+    fn default() -> PickerOptions {
+        PickerOptions::new(
+            &KeyConfig::default(),
+            &PickerConfig::default(),
+            &Theme::default(),
+        )
+        .expect("Invalid default keys")
+    }
+}
+
+/// Parse a key description like `enter`, `ctrl-e`, `alt-d` or `f2`
+fn parse_key(key: &str) -> Result<KeyEvent, Error> {
+    let lower = key.to_lowercase();
+    let single = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+
+    let parsed = match lower.as_str() {
+        "enter" => Some(KeyEvent::Char('\n')),
+        "backspace" => Some(KeyEvent::Backspace),
+        "delete" => Some(KeyEvent::Delete),
+        "insert" => Some(KeyEvent::Insert),
+        k if k.starts_with("ctrl-") => single(&k[5..]).map(KeyEvent::Ctrl),
+        k if k.starts_with("alt-") => single(&k[4..]).map(KeyEvent::Alt),
+        k if k.starts_with('f') && k.len() > 1 => k[1..].parse().ok().map(KeyEvent::F),
+        _ => None,
+    };
+
+    // Keys used for selecting, moving, cancelling and editing the query
+    let reserved = [
+        KeyEvent::Char('\t'),
+        KeyEvent::Backspace,
+        KeyEvent::Delete,
+        KeyEvent::Ctrl('a'),
+        KeyEvent::Ctrl('c'),
+        KeyEvent::Ctrl('n'),
+        KeyEvent::Ctrl('p'),
+        KeyEvent::Ctrl('u'),
+        KeyEvent::Ctrl('w'),
+    ];
+    match parsed {
+        Some(parsed) if !reserved.contains(&parsed) => Ok(parsed),
+        _ => Err(Error::InternalError(format!(
+            "Invalid key `{}` in the keys config",
+            key
+        ))),
+    }
+}
 
-/// Use skim to show multiple results, where selections is the files to select
-pub fn show_multiple_results(selections: &Vec<String>) -> Vec<usize> {
+/// Use skim to show multiple results, where selections is the files to select. Returns `None`
+/// when the picker is cancelled
+pub fn show_multiple_results(
+    selections: &Vec<String>,
+    options: &PickerOptions,
+) -> Option<Selection> {
     //    let options = SkimOptionsBuilder::default()
     //        .ansi(true)
     //        .height(Some("50%"))
@@ -22,149 +155,788 @@ pub fn show_multiple_results(selections: &Vec<String>) -> Vec<usize> {
     //
     //    selected_items.iter().map(|item| item.get_index()).collect()
 
-    let screen = AlternateScreen::to_alternate(true);
+    pick(selections, options, None)
+}
 
-    let mut matches = FuzzyMatcher::new(selections);
-    let mut selected_index = 0;
+/// Show the results with a preview of the highlighted one, `preview` gives the markdown to show
+/// for the index of a selection. Returns `None` when the picker is cancelled
+pub fn show_results_with_preview(
+    selections: &Vec<String>,
+    options: &PickerOptions,
+    preview: &dyn Fn(usize) -> String,
+) -> Option<Selection> {
+    pick(selections, options, Some(preview))
+}
 
-//    let screen = RawScreen::into_raw_mode().unwrap();
+fn pick(
+    selections: &Vec<String>,
+    options: &PickerOptions,
+    preview: Option<&dyn Fn(usize) -> String>,
+) -> Option<Selection> {
+    // Warn before the screen is taken over, the preview has other themes than the output
+    if let Some(ref theme) = options.theme.syntax_theme {
+        if preview.is_some() && options.theme.colored && !Highlighter::knows(theme) {
+            eprintln!(
+                "Unknown syntax theme `{}` for the preview, using {}",
+                theme, DEFAULT_SYNTAX_THEME
+            );
+        }
+    }
 
-    let crossterm = Crossterm::new();
-    crossterm.cursor().hide();
+    let guard = TerminalGuard::new(options.height);
 
-    let (_, term_height) = terminal().terminal_size();
-    let (_, start_cursor_pos) = crossterm.cursor().pos();
+    //    let screen = RawScreen::into_raw_mode().unwrap();
 
-    rewrite_results(&crossterm, &matches);
-    write_input(&crossterm, matches.get_search_term());
-    write_selected_index(&crossterm, &matches, selected_index);
+    let crossterm = Crossterm::new();
+
+    let mut picker = Picker {
+        crossterm,
+        matches: FuzzyMatcher::new(selections),
+        prompt: Prompt::default(),
+        region: guard.region,
+        selected_index: 0,
+        offset: 0,
+        page: 1,
+        chosen: Vec::new(),
+        preview_area: None,
+        show_score: options.show_score,
+        theme: options.theme.clone(),
+        preview: preview.map(|content| Preview {
+            content,
+            position: options.preview_position,
+            visible: options.preview,
+            scroll: 0,
+            previewed: None,
+            colored: options.theme.colored,
+            syntax_theme: options.theme.syntax_theme.clone(),
+            highlighter: None,
+            cache: HashMap::new(),
+        }),
+    };
+    picker.draw().ok();
 
-    let input = crossterm.input();
+    let mut history = History::load();
+    let input = picker.crossterm.input();
     let mut stdin = input.read_sync();
 
-    let selected_indices = loop {
-        match stdin.next() {
-            Some(InputEvent::Keyboard(KeyEvent::Char('\n'))) => {
-                // Select the top matches
-                break matches.get_matches().iter().map(|(i, ..)| *i).rev().nth(selected_index).map(|e| vec![e]).unwrap_or(Vec::new());
-            }
-            Some(InputEvent::Keyboard(KeyEvent::Up)) => {
-                selected_index = if selected_index + 1 >= matches.get_matches().len() {
-                    selected_index
+    loop {
+        let event = stdin.next();
+
+        // Finish with the action of the key
+        if let Some(InputEvent::Keyboard(ref key)) = event {
+            if let Some((_, action)) = options.action_keys.iter().find(|(k, _)| k == key) {
+                // Use the selected results, or the highlighted one if nothing is selected
+                let indices = if picker.chosen.is_empty() {
+                    picker.highlighted().map(|e| vec![e]).unwrap_or_default()
                 } else {
-                    clear_selected_index(&crossterm, &matches, selected_index);
-                    selected_index += 1;
-                    write_selected_index(&crossterm, &matches, selected_index);
-                    selected_index
+                    picker.chosen
                 };
+
+                // Remember the query for the next time
+                history.add(picker.matches.get_search_term());
+                history.save().ok();
+                break Some(Selection {
+                    indices,
+                    action: *action,
+                });
             }
-            Some(InputEvent::Keyboard(KeyEvent::Down)) => {
-                selected_index = if selected_index > 0 {
-                    clear_selected_index(&crossterm, &matches, selected_index);
-                    selected_index -= 1;
-                    write_selected_index(&crossterm, &matches, selected_index);
-                    selected_index
-                } else {
-                    selected_index
+        }
+
+        match event {
+            // Stop without a selection, also when the input is closed
+            Some(InputEvent::Keyboard(KeyEvent::Esc))
+            | Some(InputEvent::Keyboard(KeyEvent::Ctrl('c')))
+            | None => break None,
+            Some(InputEvent::Keyboard(ref key)) if *key == options.toggle_preview_key => {
+                if let Some(preview) = picker.preview.as_mut() {
+                    preview.visible = !preview.visible;
+                }
+            }
+            Some(InputEvent::Keyboard(KeyEvent::ShiftUp)) => {
+                if let Some(preview) = picker.preview.as_mut() {
+                    preview.scroll = preview.scroll.saturating_sub(1);
+                }
+            }
+            Some(InputEvent::Keyboard(KeyEvent::ShiftDown)) => {
+                // Scrolling past the end is undone when drawing
+                if let Some(preview) = picker.preview.as_mut() {
+                    preview.scroll += 1;
+                }
+            }
+            Some(InputEvent::Keyboard(KeyEvent::Char('\t'))) => {
+                // Toggle the selection of the highlighted result
+                if let Some(index) = picker.highlighted() {
+                    match picker.chosen.iter().position(|&i| i == index) {
+                        Some(position) => {
+                            picker.chosen.remove(position);
+                        }
+                        None => picker.chosen.push(index),
+                    }
+                }
+            }
+            Some(InputEvent::Keyboard(KeyEvent::Ctrl('a'))) => {
+                // Select all visible results
+                for (index, ..) in picker.matches.get_matches() {
+                    if !picker.chosen.contains(index) {
+                        picker.chosen.push(*index);
+                    }
+                }
+            }
+            Some(InputEvent::Keyboard(KeyEvent::Up))
+            | Some(InputEvent::Keyboard(KeyEvent::Ctrl('p'))) => {
+                picker.move_by(1);
+            }
+            Some(InputEvent::Keyboard(KeyEvent::Down))
+            | Some(InputEvent::Keyboard(KeyEvent::Ctrl('n'))) => {
+                picker.move_by(-1);
+            }
+            Some(InputEvent::Keyboard(KeyEvent::PageUp)) => {
+                picker.move_by(picker.page as isize);
+            }
+            Some(InputEvent::Keyboard(KeyEvent::PageDown)) => {
+                picker.move_by(-(picker.page as isize));
+            }
+            Some(InputEvent::Mouse(MouseEvent::Press(button, x, y))) => {
+                let delta = match button {
+                    MouseButton::WheelUp => 1,
+                    MouseButton::WheelDown => -1,
+                    _ => continue,
                 };
+                // Scroll the preview when the mouse is over it, the results otherwise
+                let over_preview = picker
+                    .preview_area
+                    .is_some_and(|area| x > area.x && y > area.y);
+                match picker.preview.as_mut() {
+                    Some(preview) if over_preview => {
+                        preview.scroll = (preview.scroll as isize - delta).max(0) as usize;
+                    }
+                    _ => picker.move_by(delta),
+                }
+            }
+            // Alt-Up and Alt-Down are not reported by crossterm 0.10, so Ctrl is used instead
+            Some(InputEvent::Keyboard(KeyEvent::CtrlUp)) => {
+                let query = picker.prompt.text();
+                if let Some(query) = history.previous(&query) {
+                    picker.prompt.set(query);
+                }
+            }
+            Some(InputEvent::Keyboard(KeyEvent::CtrlDown)) => {
+                if let Some(query) = history.next() {
+                    picker.prompt.set(query);
+                }
+            }
+            Some(InputEvent::Keyboard(KeyEvent::Left)) => picker.prompt.left(),
+            Some(InputEvent::Keyboard(KeyEvent::Right)) => picker.prompt.right(),
+            // Move in the query, or jump to the first or last result when the cursor is already
+            // at the start or the end, e.g. when the query is empty
+            Some(InputEvent::Keyboard(KeyEvent::Home)) => {
+                if picker.prompt.cursor == 0 {
+                    picker.selected_index = 0;
+                } else {
+                    picker.prompt.cursor = 0;
+                }
             }
-            Some(InputEvent::Keyboard(KeyEvent::Char(c))) => {
-                let mut search_term = matches.get_search_term().clone();
-                search_term.push(c);
-                matches.set_search_term(&search_term);
-                if selected_index >= matches.get_matches().len() { selected_index = if matches.get_matches().is_empty() { 0 } else { matches.get_matches().len() - 1 }}
-                rewrite_results(&crossterm, &matches);
-                write_input(&crossterm, matches.get_search_term());
-                write_selected_index(&crossterm, &matches, selected_index);
+            Some(InputEvent::Keyboard(KeyEvent::End)) => {
+                if picker.prompt.cursor == picker.prompt.chars.len() {
+                    picker.selected_index = picker.matches.get_matches().len().saturating_sub(1);
+                } else {
+                    picker.prompt.cursor = picker.prompt.chars.len();
+                }
+            }
+            Some(InputEvent::Keyboard(KeyEvent::Ctrl('w'))) => picker.prompt.delete_word(),
+            Some(InputEvent::Keyboard(KeyEvent::Ctrl('u'))) => picker.prompt.set(""),
+            Some(InputEvent::Keyboard(KeyEvent::Char(c))) if !c.is_control() => {
+                picker.prompt.insert(c);
+            }
+            Some(InputEvent::Keyboard(KeyEvent::Backspace)) => picker.prompt.backspace(),
+            Some(InputEvent::Keyboard(KeyEvent::Delete)) => picker.prompt.delete(),
+            _ => continue,
+        }
+        picker.update_matches();
+        picker.draw().ok();
+    }
+}
+
+/// Switches to the alternate screen, or makes room below the cursor when shown inline, in raw
+/// mode and restores the terminal when dropped, also when the picker panics
+struct TerminalGuard {
+    _screen: Option<AlternateScreen>,
+    _raw: Option<RawScreen>,
+    /// First row and height of the picker when it is shown inline
+    region: Option<(u16, u16)>,
+}
+
+impl TerminalGuard {
+    fn new(height: Option<Height>) -> TerminalGuard {
+        let guard = match height {
+            None => TerminalGuard {
+                _screen: AlternateScreen::to_alternate(true).ok(),
+                _raw: None,
+                region: None,
             },
-            Some(InputEvent::Keyboard(KeyEvent::Backspace)) => {
-                let mut search_term = matches.get_search_term().clone();
-                search_term.pop();
-                matches.set_search_term(&search_term);
-                if selected_index >= matches.get_matches().len() { selected_index = if matches.get_matches().is_empty() { 0 } else { matches.get_matches().len() - 1 }}
-                rewrite_results(&crossterm, &matches);
-                write_input(&crossterm, matches.get_search_term());
-                write_selected_index(&crossterm, &matches, selected_index);
+            Some(height) => {
+                let raw = RawScreen::into_raw_mode().ok();
+                let (_, terminal_height) = terminal().terminal_size();
+                let rows = height.rows(terminal_height);
+                let (_, row) = cursor().pos();
+
+                // Scroll the terminal up when there is not enough room below the cursor
+                terminal()
+                    .write("\r\n".repeat(rows.saturating_sub(1) as usize))
+                    .ok();
+                TerminalGuard {
+                    _screen: None,
+                    _raw: raw,
+                    region: Some((row.min(terminal_height.saturating_sub(rows)), rows)),
+                }
             }
-            _ => {}
+        };
+        cursor().hide().ok();
+        input().enable_mouse_mode().ok();
+        guard
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        // Leave the cursor where the picker started, so the output is written over it
+        if let Some((top, rows)) = self.region {
+            for row in top..top + rows {
+                cursor().goto(0, row).ok();
+                terminal().clear(ClearType::CurrentLine).ok();
+            }
+            cursor().goto(0, top).ok();
         }
-    };
 
-    crossterm.cursor().show();
-    selected_indices
+        // The screen itself is restored when it is dropped after this
+        input().disable_mouse_mode().ok();
+        cursor().show().ok();
+    }
+}
+
+/// Height of the inline picker
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Height {
+    Rows(u16),
+    /// Percentage of the terminal height
+    Percent(u16),
+}
+
+impl Height {
+    /// Parse a height like `15` or `40%`
+    fn parse(height: &str) -> Result<Height, Error> {
+        let parsed = if let Some(percent) = height.strip_suffix('%') {
+            percent
+                .parse()
+                .ok()
+                .filter(|p| *p <= 100)
+                .map(Height::Percent)
+        } else {
+            height.parse().ok().map(Height::Rows)
+        };
+        parsed.ok_or_else(|| {
+            Error::InternalError(format!(
+                "Invalid height `{}`, expected a number of rows or a percentage",
+                height
+            ))
+        })
+    }
+
+    /// The number of rows, at least room for one result and at most the whole terminal. Some
+    /// terminals report a height of 0, then the minimum is used
+    fn rows(self, terminal_height: u16) -> u16 {
+        let rows = match self {
+            Height::Rows(rows) => rows,
+            Height::Percent(percent) => (terminal_height as u32 * percent as u32 / 100) as u16,
+        };
+        rows.min(terminal_height).max(3)
+    }
+}
+
+/// The query typed in the picker, with the position of the cursor in it
+#[derive(Default)]
+struct Prompt {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl Prompt {
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    /// Replace the query, with the cursor at the end
+    fn set(&mut self, text: &str) {
+        self.chars = text.chars().collect();
+        self.cursor = self.chars.len();
+    }
+
+    fn insert(&mut self, c: char) {
+        self.chars.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.chars.len());
+    }
+
+    /// Delete the word before the cursor, with the whitespace after it
+    fn delete_word(&mut self) {
+        let mut start = self.cursor;
+        while start > 0 && self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        self.chars.drain(start..self.cursor);
+        self.cursor = start;
+    }
+}
+
+/// Where the preview of the highlighted result is shown
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewPosition {
+    #[default]
+    Right,
+    Bottom,
 }
 
-fn write_selected_index<'a>(
-    crossterm: &Crossterm,
-    matches: &FuzzyMatcher<'a>,
-    selected_index: usize
-) {
-    let matches = matches.get_matches();
-    if selected_index >= matches.len() {
-        return;
+/// State of the picker while it is shown
+struct Picker<'a> {
+    crossterm: Crossterm,
+    matches: FuzzyMatcher<'a>,
+    prompt: Prompt,
+    /// First row and height of the picker when it is shown inline
+    region: Option<(u16, u16)>,
+    /// Position of the highlighted result, counted from the best match
+    selected_index: usize,
+    /// Position of the result shown at the bottom of the list
+    offset: usize,
+    /// Number of results that fit on the screen
+    page: usize,
+    /// Where the preview was last drawn
+    preview_area: Option<Area>,
+    /// Indices of the results toggled for selection
+    chosen: Vec<usize>,
+    show_score: bool,
+    theme: Theme,
+    preview: Option<Preview<'a>>,
+}
+
+/// Preview of the highlighted result
+struct Preview<'a> {
+    content: &'a dyn Fn(usize) -> String,
+    position: PreviewPosition,
+    visible: bool,
+    /// First line of the content that is shown
+    scroll: usize,
+    /// The result the preview was last drawn for, the scroll is reset when this changes
+    previewed: Option<usize>,
+    /// Without colors the content is shown as is
+    colored: bool,
+    syntax_theme: Option<String>,
+    /// Loading the syntaxes takes a while, so only done when the preview is shown
+    highlighter: Option<Highlighter>,
+    cache: HashMap<usize, Vec<HighlightedLine>>,
+}
+
+/// A line of the preview, in pieces with the same color
+type HighlightedLine = Vec<(Option<Colour>, String)>;
+
+impl<'a> Preview<'a> {
+    /// The highlighted lines of the content for the index of a selection
+    fn lines(&mut self, index: usize) -> &[Vec<(Option<Colour>, String)>] {
+        let content = self.content;
+        if !self.colored {
+            return self.cache.entry(index).or_insert_with(|| {
+                content(index)
+                    .lines()
+                    .map(|line| vec![(None, line.to_string())])
+                    .collect()
+            });
+        }
+
+        let syntax_theme = &self.syntax_theme;
+        let highlighter = self
+            .highlighter
+            .get_or_insert_with(|| Highlighter::new(syntax_theme.as_ref().map(|t| t.as_str())));
+        self.cache
+            .entry(index)
+            .or_insert_with(|| highlighter.highlight(&content(index)))
     }
+}
 
-    let (_, height) = crossterm.terminal().terminal_size();
-    crossterm.cursor().goto(0, height-3-selected_index as u16);
-    let terminal = crossterm.terminal();
-    terminal.write(format!("{}>{}", Colored::Fg(Color::Red), Attribute::Reset));
+/// Theme of the preview when none is configured or the configured one is unknown
+const DEFAULT_SYNTAX_THEME: &str = "base16-ocean.dark";
+
+/// Syntax highlighting of markdown for the preview
+struct Highlighter {
+    syntaxes: SyntaxSet,
+    theme: SyntaxTheme,
 }
 
-fn clear_selected_index<'a>(
-    crossterm: &Crossterm,
-    matches: &FuzzyMatcher<'a>,
-    selected_index: usize
-) {
-    let matches = matches.get_matches();
-    if selected_index >= matches.len() {
-        return;
+impl Highlighter {
+    /// Check if the theme is one of the themes of the preview
+    fn knows(theme: &str) -> bool {
+        ThemeSet::load_defaults().themes.contains_key(theme)
+    }
+
+    /// Load the syntaxes and the theme, an unknown theme falls back to the default
+    fn new(theme: Option<&str>) -> Highlighter {
+        let mut themes = ThemeSet::load_defaults();
+        let theme = theme
+            .and_then(|t| themes.themes.remove(t))
+            .or_else(|| themes.themes.remove(DEFAULT_SYNTAX_THEME))
+            .unwrap_or_default();
+        Highlighter {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            theme,
+        }
     }
 
-    let (_, height) = crossterm.terminal().terminal_size();
-    crossterm.cursor().goto(0, height-3-selected_index as u16);
-    let terminal = crossterm.terminal();
-    terminal.write("  ");
+    /// Split the text in lines of styled pieces
+    fn highlight(&self, text: &str) -> Vec<Vec<(Option<Colour>, String)>> {
+        let syntax = self
+            .syntaxes
+            .find_syntax_by_extension("md")
+            .unwrap_or_else(|| self.syntaxes.find_syntax_plain_text());
+        let mut highlighter = HighlightLines::new(syntax, &self.theme);
+        LinesWithEndings::from(text)
+            .map(|line| {
+                highlighter
+                    .highlight(line, &self.syntaxes)
+                    .into_iter()
+                    .map(|(style, piece)| {
+                        let color = style.foreground;
+                        (
+                            Some(Colour::RGB(color.r, color.g, color.b)),
+                            piece.to_string(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
 }
 
-fn rewrite_results<'a>(
-    crossterm: &Crossterm,
-    matches: &FuzzyMatcher<'a>,
-) {
-    let (_, height) = crossterm.terminal().terminal_size();
-    crossterm.cursor().goto(0,0);
-    crossterm.terminal().clear(ClearType::CurrentLine);
-    write_results(
-        &crossterm.terminal(),
-        &matches,
-        height
-    );
+/// A part of the screen
+#[derive(Clone, Copy)]
+struct Area {
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
 }
 
-fn write_input(crossterm: &Crossterm, search_term: &String) {
-    let (_, height) = crossterm.terminal().terminal_size();
-    crossterm.cursor().goto(0,height);
-    crossterm.terminal().clear(ClearType::CurrentLine);
-    crossterm.terminal().write(format!("{}>{}{} {}{}", Colored::Fg(Color::Blue), Colored::Fg(Color::White), Attribute::Bold, search_term, Attribute::Reset));
+/// Positions of the parts of the picker on the screen
+struct Layout {
+    /// First row of the results
+    top: u16,
+    /// Width of the results, the status line and the prompt
+    width: u16,
+    /// Row of the prompt, the status line and the results are above it
+    prompt_row: u16,
+    preview: Option<Area>,
+}
+
+impl Layout {
+    fn new(top: u16, width: u16, height: u16, preview: Option<PreviewPosition>) -> Layout {
+        let bottom = top + height;
+        match preview {
+            // Split the screen in half, with a separator between the results and the preview
+            Some(PreviewPosition::Right) if width >= 20 => {
+                let list_width = width / 2;
+                Layout {
+                    top,
+                    width: list_width,
+                    prompt_row: bottom.saturating_sub(1),
+                    preview: Some(Area {
+                        x: list_width + 1,
+                        y: top,
+                        width: width - list_width - 1,
+                        height,
+                    }),
+                }
+            }
+            Some(PreviewPosition::Bottom) if height >= 10 => {
+                let preview_height = height / 2;
+                let prompt_row = bottom - preview_height - 2;
+                Layout {
+                    top,
+                    width,
+                    prompt_row,
+                    preview: Some(Area {
+                        x: 0,
+                        y: prompt_row + 2,
+                        width,
+                        height: preview_height,
+                    }),
+                }
+            }
+            _ => Layout {
+                top,
+                width,
+                prompt_row: bottom.saturating_sub(1),
+                preview: None,
+            },
+        }
+    }
 }
 
-fn write_results<'a>(
-    terminal: &Terminal,
-    matches: &FuzzyMatcher<'a>,
-    height: u16
-) {
-    // Write empty lines
-    for _ in matches.get_matches().len() ..  height as usize - 1 {
-        terminal.clear(ClearType::CurrentLine);
-        terminal.write(format!("\r\n"));
+impl<'a> Picker<'a> {
+    /// The index of the highlighted result
+    fn highlighted(&self) -> Option<usize> {
+        self.matches
+            .get_matches()
+            .iter()
+            .map(|(i, ..)| *i)
+            .rev()
+            .nth(self.selected_index)
     }
 
-    for (_, s, score, indices) in matches.get_matches().iter().take(height as usize - 1) {
-        terminal.clear(ClearType::CurrentLine);
-        terminal.write(format!("  {}\r\n", s));
+    /// Move the highlight up by the number of results, or down when negative
+    fn move_by(&mut self, delta: isize) {
+        let last = self.matches.get_matches().len().saturating_sub(1) as isize;
+        self.selected_index = (self.selected_index as isize + delta).min(last).max(0) as usize;
     }
 
-    terminal.clear(ClearType::CurrentLine);
-    terminal.write(format!("{}/{}\r\n", matches.get_matches().len(), matches.get_selections().len()));
+    /// Match the results against the query in the prompt
+    fn update_matches(&mut self) {
+        self.matches.set_search_term(&self.prompt.text());
+        let count = self.matches.get_matches().len();
+        if self.selected_index >= count {
+            self.selected_index = if count == 0 { 0 } else { count - 1 };
+        }
+    }
+
+    /// Draw the whole picker
+    fn draw(&mut self) -> crossterm::Result<()> {
+        let (width, height) = self.crossterm.terminal().terminal_size();
+        let (top, height) = self.region.unwrap_or((0, height));
+        let position = self
+            .preview
+            .as_ref()
+            .filter(|p| p.visible)
+            .map(|p| p.position);
+        let layout = Layout::new(top, width, height, position);
+        let highlighted = self.highlighted();
+        let terminal = self.crossterm.terminal();
+        let cursor = self.crossterm.cursor();
+
+        // Scroll the results so the highlighted one is visible
+        self.page = layout.prompt_row.saturating_sub(layout.top + 1).max(1) as usize;
+        if self.selected_index < self.offset {
+            self.offset = self.selected_index;
+        } else if self.selected_index >= self.offset + self.page {
+            self.offset = self.selected_index + 1 - self.page;
+        }
+        self.preview_area = layout.preview;
+
+        // The results, with the best match at the bottom
+        let matches = self.matches.get_matches();
+        for row in layout.top..layout.prompt_row.saturating_sub(1) {
+            let index = self.offset + (layout.prompt_row - 2 - row) as usize;
+            let line = match matches.len().checked_sub(index + 1).map(|i| &matches[i]) {
+                Some((i, s, score, indices)) => result_row(
+                    s,
+                    indices,
+                    self.chosen.contains(i),
+                    if self.show_score { Some(*score) } else { None },
+                    index == self.selected_index,
+                    &self.theme,
+                    layout.width as usize,
+                ),
+                None => fit("", layout.width as usize),
+            };
+            cursor.goto(0, row)?;
+            terminal.write(line)?;
+        }
+
+        let status = if self.chosen.is_empty() {
+            format!("{}/{}", matches.len(), self.matches.get_selections().len())
+        } else {
+            format!(
+                "{}/{} ({} selected)",
+                matches.len(),
+                self.matches.get_selections().len(),
+                self.chosen.len()
+            )
+        };
+        cursor.goto(0, layout.prompt_row.saturating_sub(1))?;
+        terminal.write(fit(&status, layout.width as usize))?;
+
+        // The query, scrolled so the cursor is visible
+        let chars = &self.prompt.chars;
+        let position = self.prompt.cursor;
+        let prompt_width = layout.width.saturating_sub(3) as usize;
+        let start = position.saturating_sub(prompt_width);
+        let before: String = chars[start..position].iter().collect();
+        let under = chars
+            .get(position)
+            .map_or(" ".to_string(), |c| c.to_string());
+        let after = fit(
+            &chars.iter().skip(position + 1).collect::<String>(),
+            prompt_width - (position - start),
+        );
+        let style = self.theme.query;
+        cursor.goto(0, layout.prompt_row)?;
+        terminal.write(ANSIStrings(&[
+            self.theme.prompt.paint("> "),
+            style.paint(before),
+            style.reverse().paint(under),
+            style.paint(after),
+        ]))?;
+
+        let (area, preview) = match (layout.preview, self.preview.as_mut()) {
+            (Some(area), Some(preview)) => (area, preview),
+            _ => return Ok(()),
+        };
+
+        // Separator between the results and the preview
+        if area.x > 0 {
+            for row in area.y..area.y + area.height {
+                cursor.goto(area.x - 1, row)?;
+                terminal.write("│")?;
+            }
+        } else {
+            cursor.goto(0, area.y - 1)?;
+            terminal.write("─".repeat(area.width as usize))?;
+        }
+
+        // Start at the top when another result is highlighted
+        if preview.previewed != highlighted {
+            preview.previewed = highlighted;
+            preview.scroll = 0;
+        }
+        let mut scroll = preview.scroll;
+        let lines = match highlighted {
+            Some(index) => preview.lines(index),
+            None => &[],
+        };
+        scroll = scroll.min(lines.len().saturating_sub(area.height as usize));
+
+        for row in 0..area.height {
+            cursor.goto(area.x, area.y + row)?;
+            match lines.get(scroll + row as usize) {
+                Some(line) => terminal.write(styled(line, area.width as usize))?,
+                None => terminal.write(fit("", area.width as usize))?,
+            };
+        }
+        preview.scroll = scroll;
+        Ok(())
+    }
+}
+
+/// A row of the results with the matched characters emphasized and the highlighted row in
+/// another background color, cut off or padded to the width
+fn result_row(
+    text: &str,
+    indices: &[usize],
+    chosen: bool,
+    score: Option<i64>,
+    highlighted: bool,
+    theme: &Theme,
+    width: usize,
+) -> String {
+    let base = if highlighted {
+        theme.highlight
+    } else {
+        Style::new()
+    };
+    // Keep the background of the row
+    let on_base = |style: Style| Style {
+        background: base.background,
+        is_reverse: base.is_reverse,
+        ..style
+    };
+    let score = score.map(|s| format!(" {}", s)).unwrap_or_default();
+    let text_width = width.saturating_sub(3 + score.len());
+
+    let mut pieces = vec![base.paint(" ")];
+    pieces.push(if chosen {
+        on_base(theme.selected).paint("*")
+    } else {
+        base.paint(" ")
+    });
+    pieces.push(base.paint(" "));
+
+    let mut length = 0;
+    for (i, c) in text
+        .chars()
+        .enumerate()
+        .filter(|(_, c)| !c.is_control())
+        .take(text_width)
+    {
+        if indices.contains(&i) {
+            pieces.push(on_base(theme.matched).paint(c.to_string()));
+        } else {
+            pieces.push(base.paint(c.to_string()));
+        }
+        length += 1;
+    }
+    pieces.push(base.paint(" ".repeat(text_width - length)));
+    pieces.push(base.dimmed().paint(score));
+    ANSIStrings(&pieces).to_string()
+}
+
+/// Cut off or pad the text to the width
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted: String = text
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(width)
+        .collect();
+    let length = fitted.chars().count();
+    fitted.push_str(&" ".repeat(width - length));
+    fitted
+}
+
+/// Write the styled pieces of a line in their colors, cut off or padded to the width
+fn styled(line: &[(Option<Colour>, String)], width: usize) -> String {
+    let mut result = String::new();
+    let mut length = 0;
+    for (color, piece) in line {
+        // Colored writes to the terminal directly, so use escape codes that can be put in the line
+        if let Some(color) = color {
+            result.push_str(&color.prefix().to_string());
+        }
+        for c in piece
+            .replace('\t', "    ")
+            .chars()
+            .filter(|c| !c.is_control())
+        {
+            if length == width {
+                break;
+            }
+            result.push(c);
+            length += 1;
+        }
+    }
+    result.push_str(&format!(
+        "{}{}",
+        Attribute::Reset,
+        " ".repeat(width - length)
+    ));
+    result
 }
 
 struct FuzzyMatcher<'a> {
@@ -178,7 +950,7 @@ impl<'a> FuzzyMatcher<'a> {
         let mut fuzzy = FuzzyMatcher {
             selections,
             matches: Vec::new(),
-            search_term: String::new()
+            search_term: String::new(),
         };
         fuzzy.update_matches();
         fuzzy
@@ -204,15 +976,75 @@ impl<'a> FuzzyMatcher<'a> {
     }
 
     fn update_matches(&mut self) {
-        self.matches = self.selections
+        self.matches = self
+            .selections
             .iter()
             .enumerate()
-            .filter_map(|(i, s)| match fuzzy_indices(s.as_str(), &self.search_term) {
-                Some((score, indices)) => Some((i, s, score, indices)),
-                None => None
-            })
+            .filter_map(
+                |(i, s)| match fuzzy_indices(s.as_str(), &self.search_term) {
+                    Some((score, indices)) => Some((i, s, score, indices)),
+                    None => None,
+                },
+            )
             .collect();
 
-        self.matches.sort_by(|(_, _, score_a, _), (_, _, score_b, _)| score_a.cmp(score_b));
+        self.matches
+            .sort_by(|(_, _, score_a, _), (_, _, score_b, _)| score_a.cmp(score_b));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn height_parses_rows_and_percentages() {
+        assert_eq!(Height::parse("15").unwrap(), Height::Rows(15));
+        assert_eq!(Height::parse("40%").unwrap(), Height::Percent(40));
+        assert_eq!(Height::parse("100%").unwrap(), Height::Percent(100));
+    }
+
+    #[test]
+    fn height_rejects_invalid_values() {
+        for height in &[
+            "", "%", "101%", "-5", "1.5", " 15", "15 %", "%15", "ten", "70000",
+        ] {
+            assert!(
+                Height::parse(height).is_err(),
+                "`{}` should be invalid",
+                height
+            );
+        }
+    }
+
+    #[test]
+    fn height_rows_fit_the_terminal() {
+        assert_eq!(Height::Rows(15).rows(40), 15);
+        assert_eq!(Height::Rows(60).rows(40), 40);
+        assert_eq!(Height::Rows(0).rows(40), 3);
+        assert_eq!(Height::Percent(50).rows(41), 20);
+        assert_eq!(Height::Percent(1).rows(40), 3);
+        // Some terminals report no height at all
+        assert_eq!(Height::Rows(15).rows(0), 3);
+        assert_eq!(Height::Percent(100).rows(0), 3);
+    }
+
+    #[test]
+    fn parse_key_rejects_reserved_keys() {
+        assert_eq!(parse_key("ctrl-e").unwrap(), KeyEvent::Ctrl('e'));
+        assert_eq!(parse_key("Alt-D").unwrap(), KeyEvent::Alt('d'));
+        assert_eq!(parse_key("f2").unwrap(), KeyEvent::F(2));
+        for key in &[
+            "tab",
+            "ctrl-c",
+            "ctrl-w",
+            "backspace",
+            "delete",
+            "ctrl-",
+            "f",
+            "x",
+        ] {
+            assert!(parse_key(key).is_err(), "`{}` should be rejected", key);
+        }
     }
 }
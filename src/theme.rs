@@ -0,0 +1,135 @@
+use crate::error::Error;
+use crate::project::ThemeConfig;
+use ansi_term::{Colour, Style};
+use std::env;
+use std::str::FromStr;
+
+/// When to use colors
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Only when writing to a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ColorMode, String> {
+        match s {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(format!(
+                "Unknown color mode `{}`, expected auto, always or never",
+                s
+            )),
+        }
+    }
+}
+
+impl ColorMode {
+    /// Check if colors should be used
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+                !no_color && atty::is(atty::Stream::Stdout)
+            }
+        }
+    }
+}
+
+/// The styles used in the picker and the output, without colors when they are turned off
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub colored: bool,
+    pub prompt: Style,
+    pub query: Style,
+    pub matched: Style,
+    pub highlight: Style,
+    pub selected: Style,
+    pub modified: Style,
+    /// Name of the syntax highlighting theme, the default of the highlighter when not set
+    pub syntax_theme: Option<String>,
+}
+
+impl Theme {
+    /// Create the theme from the configured colors
+    pub fn new(config: &ThemeConfig, mode: ColorMode) -> Result<Theme, Error> {
+        let theme = Theme {
+            colored: true,
+            prompt: parse_color(&config.prompt)?.normal(),
+            query: parse_color(&config.query)?.bold(),
+            matched: parse_color(&config.matched)?.bold(),
+            highlight: Style::new().on(parse_color(&config.highlight)?),
+            selected: parse_color(&config.selected)?.normal(),
+            modified: parse_color(&config.modified)?.normal(),
+            syntax_theme: config.syntax_theme.clone(),
+        };
+        if mode.enabled() {
+            return Ok(theme);
+        }
+
+        // Keep the matches and the highlighted result visible
+        Ok(Theme {
+            colored: false,
+            prompt: Style::new(),
+            query: Style::new().bold(),
+            matched: Style::new().bold(),
+            highlight: Style::new().reverse(),
+            selected: Style::new(),
+            modified: Style::new(),
+            ..theme
+        })
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::new(&ThemeConfig::default(), ColorMode::default()).expect("Invalid default theme")
+    }
+}
+
+/// Parse a color like `red`, `bright-blue`, `208` or `#ff8800`
+fn parse_color(color: &str) -> Result<Colour, Error> {
+    let lower = color.trim().to_lowercase();
+    let (bright, name) = match lower.strip_prefix("bright-") {
+        Some(name) => (true, name),
+        None => (false, lower.as_str()),
+    };
+
+    let base = match name {
+        "black" => Some(0),
+        "red" => Some(1),
+        "green" => Some(2),
+        "yellow" => Some(3),
+        "blue" => Some(4),
+        "magenta" | "purple" => Some(5),
+        "cyan" => Some(6),
+        "white" => Some(7),
+        _ => None,
+    };
+    let parsed = match base {
+        Some(base) if bright => Some(Colour::Fixed(base + 8)),
+        Some(base) => Some(Colour::Fixed(base)),
+        None if bright => None,
+        None if name.starts_with('#') && name.len() == 7 && name.is_ascii() => {
+            let channel = |i: usize| u8::from_str_radix(&name[i..i + 2], 16).ok();
+            match (channel(1), channel(3), channel(5)) {
+                (Some(r), Some(g), Some(b)) => Some(Colour::RGB(r, g, b)),
+                _ => None,
+            }
+        }
+        None => name.parse().ok().map(Colour::Fixed),
+    };
+
+    parsed.ok_or_else(|| {
+        Error::InternalError(format!("Invalid color `{}` in the theme config", color))
+    })
+}
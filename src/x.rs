@@ -1,17 +1,22 @@
 use crate::error::Error;
 use crate::error::Error::InternalError;
 use crate::git;
+use crate::index;
 use crate::project;
+use crate::query;
+use crate::search;
 use crate::snippet;
 
 use std::process::Command;
 
+use std::collections::HashSet;
 use std::env;
 use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::Write;
 use std::path;
+use std::time::SystemTime;
 
 #[derive(Debug)]
 pub enum OpCode<'a> {
@@ -27,91 +32,476 @@ pub enum OpCode<'a> {
     SaveSnippets,
 }
 
+/// A snippet file found in one of the snippet locations
+#[derive(Debug)]
+pub struct SnippetFile {
+    pub path: path::PathBuf,
+    /// Folder relative to the snippet location, `None` when in the root
+    pub folder: Option<String>,
+    pub modified: SystemTime,
+    pub size: u64,
+    /// Level of the headings to split the file in snippets at
+    pub split_level: Option<usize>,
+}
+
 /// Find the snippets associated with the project
-pub fn find_snippets(project: &project::Project) -> Result<Vec<fs::DirEntry>, Error> {
+pub fn find_snippets(project: &project::Project) -> Result<Vec<SnippetFile>, Error> {
     // Crawl through directory that is set as project root
-    let mut res: Vec<fs::DirEntry> = Vec::new();
+    let mut res: Vec<SnippetFile> = Vec::new();
 
     // Read the entries in the folder
     for snippet_location in project.locations.iter() {
-        println!("Finding snippets in {},", &snippet_location.local.as_str());
-        let mut entries: Vec<fs::DirEntry> = fs::read_dir(&snippet_location.local)?
-            .filter_map(|x| x.ok())
-            .collect();
-
-        // For each of the entries
-        let mut entries: Vec<_> = entries
-            .into_iter()
-            .filter_map(|e| {
-                let dir_ent = e;
-
-                // Get the path
-                let path = dir_ent.path();
-                // Get the extension
-                let ext_opt = path.extension();
-                if let Some(ext) = ext_opt {
-                    if let Some(s) = ext.to_str() {
-                        // Add to list if files match extension
-                        if s == snippet_location.ext {
-                            return Some(dir_ent);
-                        }
-                    }
-                }
-                return None;
-            })
-            .collect();
-        res.append(&mut entries);
+        // Not on stdout, so the output can be used in scripts
+        eprintln!("Finding snippets in {},", &snippet_location.local.as_str());
+        let root = path::Path::new(&snippet_location.local);
+        let mut visited = HashSet::new();
+        visited.insert(root.canonicalize()?);
+        find_snippets_in(snippet_location, root, 0, &mut visited, &mut res)?;
     }
     Ok(res)
 }
 
-/// Load snippets from the dir entries
+/// Recursively find the snippets in a folder of the snippet location
+fn find_snippets_in(
+    snippet_location: &project::SnippetLocation,
+    dir: &path::Path,
+    depth: usize,
+    visited: &mut HashSet<path::PathBuf>,
+    res: &mut Vec<SnippetFile>,
+) -> Result<(), Error> {
+    // Only the location itself has to be readable, a folder below it is skipped with a warning
+    let entries: Vec<fs::DirEntry> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|x| x.ok()).collect(),
+        Err(e) if depth > 0 => {
+            eprintln!("Skipping {}: {}", dir.display(), e);
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    // For each of the entries
+    for dir_ent in entries {
+        // Get the path
+        let path = dir_ent.path();
+        let file_type = match dir_ent.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+
+        // Resolve symlinks only if they should be followed
+        let is_dir = if file_type.is_symlink() {
+            snippet_location.follow_symlinks == Some(true)
+                && fs::metadata(&path).map(|m| m.is_dir()).unwrap_or(false)
+        } else {
+            file_type.is_dir()
+        };
+
+        if is_dir {
+            // Skip hidden folders like .git
+            let hidden = dir_ent
+                .file_name()
+                .to_str()
+                .is_none_or(|n| n.starts_with('.'));
+            let too_deep = snippet_location.max_depth.is_some_and(|max| depth >= max);
+            if hidden || too_deep {
+                continue;
+            }
+
+            // Guard against symlink cycles
+            let canonical = match path.canonicalize() {
+                Ok(canonical) => canonical,
+                Err(e) => {
+                    eprintln!("Skipping {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if visited.insert(canonical) {
+                find_snippets_in(snippet_location, &path, depth + 1, visited, res)?;
+            }
+            continue;
+        }
+
+        // Get the extension
+        let ext_opt = path.extension();
+        if let Some(ext) = ext_opt {
+            if let Some(s) = ext.to_str() {
+                // Add to list if files match extension
+                if s == snippet_location.ext {
+                    // Skip files that cannot be read, like broken symlinks
+                    let metadata = match fs::metadata(&path) {
+                        Ok(metadata) => metadata,
+                        Err(_) => continue,
+                    };
+                    let folder = path
+                        .parent()
+                        .and_then(|p| p.strip_prefix(&snippet_location.local).ok())
+                        .and_then(|p| p.to_str())
+                        .filter(|p| !p.is_empty())
+                        .map(|p| p.to_string());
+                    res.push(SnippetFile {
+                        path,
+                        folder,
+                        modified: metadata.modified()?,
+                        size: metadata.len(),
+                        split_level: snippet_location.split_level,
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Load snippets from the found snippet files
 pub fn load_snippets(
-    dir_entries: &Vec<fs::DirEntry>,
-    keywords: &Vec<String>,
+    files: &[SnippetFile],
+    keywords: &[String],
+    options: &search::SearchOptions,
+    project: &project::Project,
+    index: &mut index::Index,
 ) -> Result<Vec<snippet::Snippet>, Error> {
-    let keyword_slice = keywords.as_slice();
+    let query = query::Query::parse(keywords, options.match_all)?
+        .expand(&|term| project.synonyms(term), &mut Vec::new());
+    let terms: Vec<String> = query.positive_terms().into_iter().map(|(_, t)| t).collect();
 
-    // Get all tags for entries
-    let mut tag_with_entries: Vec<(u32, &fs::DirEntry, Vec<String>)> = Vec::new();
-    for entry in dir_entries {
-        // Read the tags
-        let tags = snippet::read_tags(entry.path().to_str().unwrap())?;
+    // Get the snippets for the files
+    let mut documents: Vec<search::Document> = Vec::new();
+    for file in files {
+        // Read the metadata, containing the tags, from the index if the file did not change
+        let metadata = match index.metadata(
+            file.path.to_str().unwrap(),
+            file.modified,
+            file.size,
+            snippet::read_metadata,
+        ) {
+            Ok(metadata) => metadata,
+            // Skip a file that cannot be read instead of failing the whole search
+            Err(e) => {
+                eprintln!("Skipping {}: {}", file.path.display(), e);
+                continue;
+            }
+        };
+        let path = file.path.to_str().unwrap();
+
+        // Use the sections of the file as separate snippets when it is split
+        let headings: Vec<snippet::Heading> = match file.split_level {
+            Some(level) => index
+                .headings(path, file.modified, file.size, snippet::read_headings)?
+                .into_iter()
+                .filter(|h| h.level == level)
+                .collect(),
+            None => Vec::new(),
+        };
+        let mut snippets = Vec::new();
+        if headings.is_empty() {
+            snippets.push(snippet::Snippet {
+                tags: project.canonical_tags(&metadata.tags),
+                folder: file.folder.clone(),
+                modified: Some(file.modified),
+                ..snippet::Snippet::from_metadata(path.to_string(), metadata)
+            });
+        } else {
+            for heading in headings {
+                // The tags of the file are inherited by its sections
+                let mut tags = metadata.tags.clone();
+                tags.extend(
+                    heading
+                        .tags
+                        .into_iter()
+                        .filter(|t| !metadata.tags.contains(t)),
+                );
+                snippets.push(snippet::Snippet {
+                    anchor: Some(heading.anchor),
+                    tags: project.canonical_tags(&tags),
+                    title: Some(heading.title),
+                    description: heading.description,
+                    code_languages: heading.code_languages,
+                    folder: file.folder.clone(),
+                    modified: Some(file.modified),
+                    ..snippet::Snippet::from_metadata(path.to_string(), metadata.clone())
+                });
+            }
+        }
 
-        // If tag is in the snippet, or no tags are given
-        // Filter which don't contain the keyword
-        let tag_count : u32 = tags.iter()
-            .fold(0, |x, tag| x + if keyword_slice.contains(tag) { 1 } else { 0 });
-        if keyword_slice.is_empty() || tag_count > 0 {
-            tag_with_entries.push((tag_count, entry, snippet::read_tags(entry.path().to_str().unwrap())?));
+        for mut snippet in snippets {
+            // Search the body if requested
+            let document = if options.body && query != query::Query::All {
+                let anchor = snippet.anchor.as_deref();
+                let body = snippet::read_body(&snippet.name, anchor)?;
+                snippet.body_matches = search::search_body(&body, &terms, options.strictness);
+                search::Document::new(snippet, Some(&body), options.strictness)
+            } else {
+                search::Document::new(snippet, None, options.strictness)
+            };
+            documents.push(document);
         }
     }
 
-    // Sort by number of matched tags
-    tag_with_entries.sort_by(|a, b| b.0.cmp(&a.0) );
+    // Only keep the matching snippets, best matches first
+    Ok(search::rank(
+        documents,
+        &query,
+        &project.search_config(),
+        options.strictness,
+    ))
+}
+
+/// The keywords of the query that have synonyms, with the synonyms they are expanded to
+pub fn expand_aliases(
+    project: &project::Project,
+    keywords: &[String],
+    options: &search::SearchOptions,
+) -> Result<Vec<(String, Vec<String>)>, Error> {
+    let mut expanded = Vec::new();
+    query::Query::parse(keywords, options.match_all)?
+        .expand(&|term| project.synonyms(term), &mut expanded);
+    Ok(expanded)
+}
 
-    // This maps the files and tags, to a snippet
-    let result = tag_with_entries
+//// Edit snippets, a section is opened at the heading with the anchor
+pub fn edit_snippet(
+    program: &str,
+    full_path: &path::Path,
+    anchor: Option<&str>,
+) -> Result<(), Error> {
+    let final_editor = default_editor(program);
+    let mut command = Command::new(&final_editor);
+    if let (Some(anchor), true) = (anchor, supports_line_argument(&final_editor)) {
+        let line = snippet::locate(full_path.to_str().unwrap(), anchor)?;
+        command.arg(format!("+{}", line));
+    }
+    let _output = command.arg(full_path).spawn()?.wait_with_output()?;
+
+    Ok(())
+}
+
+/// Check if the editor opens a file at a line with `+<line>`, others would open a file named
+/// like that instead
+fn supports_line_argument(editor: &str) -> bool {
+    let name = path::Path::new(editor)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(editor);
+    ["vi", "vim", "nvim", "nano", "emacs", "kak"].contains(&name)
+}
+
+/// The snippet location the file is in
+fn location_of<'a>(
+    project: &'a project::Project,
+    full_path: &path::Path,
+) -> Option<&'a project::SnippetLocation> {
+    project
+        .locations
         .iter()
-        .map(|(count, entry, tags)| {
-            snippet::Snippet::new(entry.path().to_str().unwrap().to_string(), &tags)
-        })
-        .collect();
+        .find(|l| full_path.starts_with(&l.local))
+}
 
-    Ok(result)
+/// The path of the snippet when it is a whole file, sections have to be changed in their file
+fn file_path(snippet: &snippet::Snippet) -> Result<&path::Path, Error> {
+    match snippet.anchor {
+        Some(_) => Err(InternalError(format!(
+            "{} is a section, edit {} instead",
+            snippet.full_name(),
+            snippet.name
+        ))),
+        None => Ok(path::Path::new(&snippet.name)),
+    }
 }
 
-//// Edit snippets
-pub fn edit_snippet(program: &str, full_path: &path::Path) -> Result<(), Error> {
-    let final_editor = default_editor(program);
-    let _output = Command::new(final_editor)
-        .arg(&full_path)
-        .spawn()?
-        .wait_with_output()?;
+/// Delete a snippet, with `git rm` when it is tracked in a git location
+pub fn delete_snippet(project: &project::Project, snippet: &snippet::Snippet) -> Result<(), Error> {
+    let full_path = file_path(snippet)?;
+    match location_of(project, full_path) {
+        Some(location)
+            if location.git == Some(true) && git::git_is_tracked(location, full_path) =>
+        {
+            git::git_rm(location, full_path, false)?
+        }
+        _ => fs::remove_file(full_path)?,
+    }
+    Ok(())
+}
+
+/// Rename a snippet in its folder, the name can contain subfolders. The extension of the
+/// location is added when the name has none, another extension is refused as the snippet would
+/// no longer be found. Returns the new path
+pub fn rename_snippet(
+    project: &project::Project,
+    snippet: &snippet::Snippet,
+    new_name: &str,
+) -> Result<path::PathBuf, Error> {
+    let full_path = file_path(snippet)?;
+    let location = location_of(project, full_path).ok_or_else(|| {
+        InternalError(format!(
+            "{} is not in a snippet location",
+            full_path.display()
+        ))
+    })?;
+
+    // Stay in the location
+    let name = path::Path::new(new_name);
+    if name.is_absolute() || name.components().any(|c| c == path::Component::ParentDir) {
+        return Err(InternalError(format!(
+            "Invalid name `{}`, it has to be in the folder of the snippet",
+            new_name
+        )));
+    }
+    let mut to = full_path.parent().unwrap_or(full_path).join(name);
+    match to.extension() {
+        None => {
+            to.set_extension(&location.ext);
+        }
+        Some(ext) if ext != location.ext.as_str() => {
+            return Err(InternalError(format!(
+                "Invalid name `{}`, the snippets in {} end in .{}",
+                new_name, location.local, location.ext
+            )));
+        }
+        Some(_) => {}
+    }
 
+    move_file(project, full_path, &to)?;
+    Ok(to)
+}
+
+/// Move a snippet to the same folder in another location, given by its path. Returns the new
+/// path
+pub fn move_snippet(
+    project: &project::Project,
+    snippet: &snippet::Snippet,
+    location: &str,
+) -> Result<path::PathBuf, Error> {
+    let full_path = file_path(snippet)?;
+    let from = location_of(project, full_path).ok_or_else(|| {
+        InternalError(format!(
+            "{} is not in a snippet location",
+            full_path.display()
+        ))
+    })?;
+    let to = project
+        .locations
+        .iter()
+        .find(|l| l.local.trim_end_matches('/') == location.trim_end_matches('/'))
+        .ok_or_else(|| {
+            let locations: Vec<&str> = project.locations.iter().map(|l| l.local.as_str()).collect();
+            InternalError(format!(
+                "Unknown location `{}`, expected one of {}",
+                location,
+                locations.join(", ")
+            ))
+        })?;
+    if from.local == to.local {
+        return Err(InternalError(format!(
+            "{} is already in {}",
+            full_path.display(),
+            to.local
+        )));
+    }
+
+    let relative = full_path.strip_prefix(&from.local).unwrap_or(full_path);
+    let new_path = path::Path::new(&to.local).join(relative);
+    move_file(project, full_path, &new_path)?;
+    Ok(new_path)
+}
+
+/// Move the snippet file, with `git mv` within a git location. Between locations the change is
+/// staged in the git locations. An existing file is never overwritten
+fn move_file(project: &project::Project, from: &path::Path, to: &path::Path) -> Result<(), Error> {
+    if to.exists() {
+        return Err(InternalError(format!("{} already exists", to.display())));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let from_location = location_of(project, from);
+    let to_location = location_of(project, to);
+    let tracked = from_location
+        .filter(|l| l.git == Some(true))
+        .is_some_and(|l| git::git_is_tracked(l, from));
+    let same_location = from_location.map(|l| &l.local) == to_location.map(|l| &l.local);
+
+    match from_location {
+        Some(location) if tracked && same_location => git::git_mv(location, from, to)?,
+        _ => {
+            // Renaming does not work across file systems
+            if fs::rename(from, to).is_err() {
+                fs::copy(from, to)?;
+                fs::remove_file(from)?;
+            }
+            if let (Some(f), true) = (from_location, tracked) {
+                git::git_rm(f, from, true)?;
+            }
+            // An untracked snippet stays untracked within its location
+            if let Some(t) = to_location.filter(|l| l.git == Some(true)) {
+                if !same_location {
+                    git::git_add_path(t, to)?;
+                }
+            }
+        }
+    }
     Ok(())
 }
 
+/// Run the code with the interpreter, a command that gets the path of a file with the code and
+/// the extension. Returns the exit code of the interpreter
+pub fn run_code(interpreter: &str, code: &str, extension: &str) -> Result<i32, Error> {
+    let mut parts = interpreter.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| InternalError("Empty interpreter command".to_string()))?;
+
+    // Use a file instead of stdin, so the code can still read from the terminal
+    let extension = Some(extension)
+        .filter(|e| !e.is_empty() && e.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or("sh");
+    let (script, mut file) = create_script_file(extension)?;
+    let written = file.write_all(code.as_bytes());
+    drop(file);
+    let status = written.map(|_| Command::new(program).args(parts).arg(&script).status());
+    // Cleaning up must not hide why the code did not run
+    fs::remove_file(&script).ok();
+
+    let status =
+        status?.map_err(|e| InternalError(format!("Failed to run `{}`: {}", interpreter, e)))?;
+    // Killed by a signal
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Create a new file for a script in the temp dir, only readable by the user. The file is never
+/// an existing one, as the temp dir is shared with other users
+fn create_script_file(extension: &str) -> Result<(path::PathBuf, File), Error> {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let mut last_error = None;
+    for attempt in 0..100u32 {
+        let script = env::temp_dir().join(format!(
+            "rusty-x-{}-{:08x}.{}",
+            std::process::id(),
+            nanos.wrapping_add(attempt.wrapping_mul(0x9e37_79b9)),
+            extension
+        ));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        match options.open(&script) {
+            Ok(file) => return Ok((script, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => last_error = Some(e),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(InternalError(format!(
+        "Could not create a script file in the temp dir: {}",
+        last_error.map_or(String::new(), |e| e.to_string())
+    )))
+}
+
 /// New snippet
 pub fn new_snippet(program: &str, working_dir: &path::Path) -> Result<(), Error> {
     let final_editor = default_editor(program);
@@ -139,6 +529,7 @@ pub fn start_operation(
     code: &OpCode,
     project: &project::Project,
     keywords: Vec<String>,
+    options: &search::SearchOptions,
 ) -> Result<Vec<snippet::Snippet>, Error> {
     // Match on operation
     let result = match code {
@@ -158,7 +549,7 @@ pub fn start_operation(
             }
 
             // Open vim on location
-            edit_snippet("vim", &full_path)?;
+            edit_snippet("vim", &full_path, None)?;
 
             let snippet =
                 snippet::Snippet::new(full_path.into_os_string().into_string().unwrap(), &keywords);
@@ -176,7 +567,13 @@ pub fn start_operation(
         // List snippets
         OpCode::ListSnippets(_) => {
             let files = find_snippets(&project)?;
-            let snippets = load_snippets(&files, &keywords)?;
+
+            // Use the index to skip parsing unchanged files
+            let mut index = index::Index::load();
+            let snippets = load_snippets(&files, &keywords, options, project, &mut index)?;
+            index.retain(&files.iter().filter_map(|f| f.path.to_str()).collect());
+            // The index is only a cache, searching still works when it cannot be written
+            index.save().ok();
 
             Ok(snippets)
         }
@@ -225,3 +622,140 @@ pub fn start_operation(
     };
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A project with two locations in a new folder in the temp dir, removed when dropped
+    struct TestProject {
+        root: path::PathBuf,
+        project: project::Project,
+    }
+
+    impl TestProject {
+        fn new(name: &str) -> TestProject {
+            let root =
+                env::temp_dir().join(format!("rusty-x-test-{}-{}", std::process::id(), name));
+            let locations = ["one", "two"]
+                .iter()
+                .map(|l| {
+                    let local = root.join(l);
+                    fs::create_dir_all(&local).unwrap();
+                    project::SnippetLocation {
+                        git: Some(false),
+                        ..project::SnippetLocation::default(&local.to_str().unwrap().to_string())
+                    }
+                })
+                .collect();
+            TestProject {
+                root,
+                project: project::Project {
+                    locations,
+                    search: None,
+                    keys: None,
+                    picker: None,
+                    theme: None,
+                    clipboard: None,
+                    run: None,
+                    aliases: Default::default(),
+                },
+            }
+        }
+
+        /// Write a snippet in the location and return it
+        fn snippet(&self, location: usize, name: &str) -> snippet::Snippet {
+            let path = path::Path::new(&self.project.locations[location].local).join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "test\n# Heading\n").unwrap();
+            snippet::Snippet::new(path.to_str().unwrap().to_string(), &Vec::new())
+        }
+
+        fn exists(&self, location: usize, name: &str) -> bool {
+            path::Path::new(&self.project.locations[location].local)
+                .join(name)
+                .exists()
+        }
+    }
+
+    impl Drop for TestProject {
+        fn drop(&mut self) {
+            fs::remove_dir_all(&self.root).ok();
+        }
+    }
+
+    #[test]
+    fn rename_snippet_in_its_folder() {
+        let test = TestProject::new("rename");
+        let snippet = test.snippet(0, "docker/prune.md");
+        let to = rename_snippet(&test.project, &snippet, "images/clean").unwrap();
+        let local = &test.project.locations[0].local;
+        assert_eq!(to, path::Path::new(local).join("docker/images/clean.md"));
+        assert!(test.exists(0, "docker/images/clean.md"));
+        assert!(!test.exists(0, "docker/prune.md"));
+    }
+
+    #[test]
+    fn rename_snippet_stays_in_the_location() {
+        let test = TestProject::new("rename-outside");
+        let snippet = test.snippet(0, "docker/prune.md");
+        for name in &["../prune", "../../two/prune", "a/../../prune", "/tmp/prune"] {
+            assert!(
+                rename_snippet(&test.project, &snippet, name).is_err(),
+                "{}",
+                name
+            );
+        }
+        assert!(test.exists(0, "docker/prune.md"));
+    }
+
+    #[test]
+    fn rename_snippet_refuses_another_extension() {
+        let test = TestProject::new("rename-extension");
+        let snippet = test.snippet(0, "prune.md");
+        assert!(rename_snippet(&test.project, &snippet, "prune.txt").is_err());
+        assert!(!test.exists(0, "prune.txt"));
+        assert!(rename_snippet(&test.project, &snippet, "clean.md").is_ok());
+        assert!(test.exists(0, "clean.md"));
+    }
+
+    #[test]
+    fn rename_and_move_never_overwrite() {
+        let test = TestProject::new("overwrite");
+        let snippet = test.snippet(0, "docker/prune.md");
+        test.snippet(0, "docker/clean.md");
+        test.snippet(1, "docker/prune.md");
+        assert!(rename_snippet(&test.project, &snippet, "clean").is_err());
+        let other = test.project.locations[1].local.clone();
+        assert!(move_snippet(&test.project, &snippet, &other).is_err());
+        assert!(test.exists(0, "docker/prune.md"));
+    }
+
+    #[test]
+    fn move_snippet_to_the_same_folder_in_another_location() {
+        let test = TestProject::new("move");
+        let snippet = test.snippet(0, "docker/prune.md");
+        let here = test.project.locations[0].local.clone();
+        assert!(move_snippet(&test.project, &snippet, &here).is_err());
+        assert!(move_snippet(&test.project, &snippet, "/unknown").is_err());
+
+        let other = test.project.locations[1].local.clone() + "/";
+        let to = move_snippet(&test.project, &snippet, &other).unwrap();
+        let local = &test.project.locations[1].local;
+        assert_eq!(to, path::Path::new(local).join("docker/prune.md"));
+        assert!(test.exists(1, "docker/prune.md"));
+        assert!(!test.exists(0, "docker/prune.md"));
+    }
+
+    #[test]
+    fn sections_are_not_changed_on_their_own() {
+        let test = TestProject::new("sections");
+        let mut snippet = test.snippet(0, "cheats.md");
+        snippet.anchor = Some("heading".to_string());
+        let other = test.project.locations[1].local.clone();
+        assert!(rename_snippet(&test.project, &snippet, "other").is_err());
+        assert!(move_snippet(&test.project, &snippet, &other).is_err());
+        assert!(delete_snippet(&test.project, &snippet).is_err());
+        assert!(test.exists(0, "cheats.md"));
+    }
+}